@@ -1,7 +1,7 @@
 //! The CLI for `st`.
 
 use crate::{
-    config::{prompt_for_configuration, StConfig},
+    config::{prompt_for_configuration, RepoConfig, StConfig},
     ctx::StContext,
     errors::{StError, StResult},
     subcommands::Subcommands,
@@ -34,8 +34,13 @@ impl Cli {
         // Load the active repository.
         let repo = crate::git::active_repository().ok_or(StError::NotAGitRepository)?;
         let config = Self::load_cfg_or_initialize()?;
-        let context = Self::load_ctx_or_initialize(config, &repo)?;
-        self.subcommand.run(context).await
+        let repo_config = RepoConfig::load(&repo)?;
+        let mut context = Self::load_ctx_or_initialize(config, repo_config, &repo)?;
+
+        // Persist the context once the subcommand succeeds. Subcommands that may fail after
+        // moving branches save the context themselves.
+        self.subcommand.run(&mut context).await?;
+        context.save()
     }
 
     /// Loads the [StConfig]. If the config does not exist or is the default config, prompts
@@ -55,19 +60,30 @@ impl Cli {
     /// prompts the user to set up the repository with `st`.
     ///
     /// ## Takes
+    /// - `config` - The global `st` config.
+    /// - `repo_config` - The per-repository `st` config.
     /// - `repo` - The repository to load the context for.
     ///
     /// ## Returns
     /// - `Result<StContext>` - The context for the repository.
     pub(crate) fn load_ctx_or_initialize(
         config: StConfig,
+        repo_config: RepoConfig,
         repo: &Repository,
-    ) -> StResult<StContext> {
+    ) -> StResult<StContext<'_>> {
         // Attempt to load the repository store, or create a new one if it doesn't exist.
-        if let Some(ctx) = StContext::try_load(config.clone(), repo)? {
-            return Ok(ctx);
-        }
+        StContext::load_or_initialize(config, repo_config, repo, || Self::prompt_for_trunk(repo))
+    }
 
+    /// Prompts the user to select the trunk branch of a [Repository] that is not yet set up
+    /// with `st`.
+    ///
+    /// ## Takes
+    /// - `repo` - The repository to set up.
+    ///
+    /// ## Returns
+    /// - `Result<String>` - The name of the trunk branch.
+    fn prompt_for_trunk(repo: &Repository) -> StResult<String> {
         let setup_message = format!(
             "Repo not configured with `{}`. Select the trunk branch for the repository.",
             Blue.paint("st")
@@ -91,8 +107,7 @@ impl Cli {
             "\nSuccessfully set up repository with `{}`. Happy stacking ✨📚\n",
             Blue.paint("st")
         );
-
-        Ok(StContext::fresh(config, repo, trunk_branch))
+        Ok(trunk_branch)
     }
 }
 
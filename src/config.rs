@@ -1,6 +1,12 @@
 //! Contains the global configuration for `st`.
 
-use crate::{constants::ST_CFG_FILE_NAME, errors::StResult};
+use crate::{
+    constants::{ST_CFG_FILE_NAME, ST_REPO_CFG_FILE_NAME},
+    ctx::write_atomic,
+    errors::StResult,
+    git::RepositoryExt,
+};
+use git2::Repository;
 use nu_ansi_term::Color;
 use serde::{Deserialize, Serialize};
 use std::{fs, io, path::PathBuf, process::Command};
@@ -16,6 +22,25 @@ pub(crate) const DEFAULT_CONFIG_PRETTY: &str = r#"# GitHub personal access token
 # If you're planning to use st with private repositories, you'll need to add the full `repo` scope.
 github_token = """#;
 
+pub(crate) const DEFAULT_REPO_CONFIG_PRETTY: &str = r#"[restack]
+# How branches are restacked onto their parents.
+#
+# - "rebase": Replay each branch's commits on top of its parent. Pushing restacked branches requires
+#             `st submit --force`.
+# - "merge":  Merge each parent into its child branch, so that pushes never need to be forced.
+#             Commands that rewrite history, such as `st move` and `st squash`, always rebase.
+#             `st squash`, `st fold`, `st split` and `st absorb` refuse branches that contain
+#             merge commits, until they are rebased onto their parent.
+strategy = "rebase"
+
+[metadata]
+# Where the stack tree is stored.
+#
+# - "file": A file within `.git`, which never leaves this machine.
+# - "ref":  A commit under `refs/st/metadata`, which can be exchanged with remotes through
+#           `st metadata push` and `st metadata pull`.
+backend = "file""#;
+
 #[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct StConfig {
     /// GitHub personal access token.
@@ -79,6 +104,90 @@ impl Drop for StConfig {
     }
 }
 
+/// The per-repository configuration for `st`, stored within the repository's `.git` directory.
+#[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RepoConfig {
+    /// Configuration for restacking branches.
+    #[serde(default)]
+    pub restack: RestackConfig,
+    /// Configuration for storing the stack tree.
+    #[serde(default)]
+    pub metadata: MetadataConfig,
+}
+
+/// Configuration for restacking branches.
+#[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RestackConfig {
+    /// The strategy used to restack branches onto their parents.
+    #[serde(default)]
+    pub strategy: RestackStrategy,
+}
+
+/// The strategy used to restack branches onto their parents.
+#[derive(Default, Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestackStrategy {
+    /// Rebase branches onto their parents.
+    #[default]
+    Rebase,
+    /// Merge parents into their child branches.
+    ///
+    /// Branches that contain merge commits cannot be squashed, folded, split or absorbed into,
+    /// as their commits can no longer be told apart from their parent's.
+    Merge,
+}
+
+/// Configuration for storing the stack tree.
+#[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MetadataConfig {
+    /// The backend that the stack tree is stored in.
+    #[serde(default)]
+    pub backend: MetadataBackend,
+}
+
+/// The backend that the stack tree is stored in.
+#[derive(Default, Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MetadataBackend {
+    /// A file within `.git`.
+    #[default]
+    File,
+    /// A commit under `refs/st/metadata`.
+    Ref,
+}
+
+impl RepoConfig {
+    /// Returns the path to the per-repository configuration for the given [Repository], which is
+    /// shared by all worktrees.
+    ///
+    /// ## Returns
+    /// - `PathBuf` - The path to the per-repository configuration.
+    pub fn path(repository: &Repository) -> PathBuf {
+        repository.common_dir().join(ST_REPO_CFG_FILE_NAME)
+    }
+
+    /// Loads the per-repository configuration for the given [Repository], falling back to the
+    /// default configuration if it does not exist.
+    pub fn load(repository: &Repository) -> StResult<Self> {
+        let config_path = Self::path(repository);
+        match fs::read_to_string(config_path) {
+            Ok(contents) => toml::from_str(&contents).map_err(|e| {
+                StConfigError::FailedToLoad(io::Error::new(io::ErrorKind::InvalidData, e)).into()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Persists the per-repository configuration for the given [Repository].
+    pub fn save(&self, repository: &Repository) -> StResult<()> {
+        write_atomic(&Self::path(repository), &toml::to_string_pretty(self)?)
+    }
+}
+
 /// Error type for global [StConfig] operations.
 #[derive(Error, Debug)]
 pub enum StConfigError {
@@ -116,13 +225,44 @@ pub fn prompt_for_configuration(existing_config: Option<&str>) -> StResult<StCon
     Ok(config)
 }
 
+/// Prompts the user to edit the per-repository configuration for `st`.
+///
+/// ## Returns
+/// - `Result<RepoConfig>` - The edited per-repository config.
+pub fn prompt_for_repo_configuration(existing_config: Option<&str>) -> StResult<RepoConfig> {
+    let setup_text = format!(
+        "Edit the configuration of `{}` for this repository.",
+        Color::Blue.paint("st")
+    );
+
+    let ser_cfg = inquire::Editor::new(&setup_text)
+        .with_file_extension(".toml")
+        .with_predefined_text(existing_config.unwrap_or(DEFAULT_REPO_CONFIG_PRETTY))
+        .prompt()?;
+
+    Ok(toml::from_str(&ser_cfg)?)
+}
+
 #[cfg(test)]
 mod test {
-    use super::{StConfig, DEFAULT_CONFIG_PRETTY};
+    use super::{
+        MetadataBackend, RepoConfig, RestackStrategy, StConfig, DEFAULT_CONFIG_PRETTY,
+        DEFAULT_REPO_CONFIG_PRETTY,
+    };
 
     #[test]
     fn pretty_default_config_is_valid() {
         let de = toml::from_str::<StConfig>(DEFAULT_CONFIG_PRETTY);
         assert!(de.is_ok());
     }
+
+    #[test]
+    fn pretty_default_repo_config_is_valid() {
+        let de = toml::from_str::<RepoConfig>(DEFAULT_REPO_CONFIG_PRETTY).unwrap();
+        assert_eq!(de, RepoConfig::default());
+
+        let de = toml::from_str::<RepoConfig>("[restack]\nstrategy = \"merge\"").unwrap();
+        assert_eq!(de.restack.strategy, RestackStrategy::Merge);
+        assert_eq!(de.metadata.backend, MetadataBackend::File);
+    }
 }
@@ -2,15 +2,38 @@
 
 use nu_ansi_term::Color;
 
-/// Name of the `.git` directory.
-pub(crate) const GIT_DIR: &str = ".git";
-
 /// Name of the global config file.
 pub(crate) const ST_CFG_FILE_NAME: &str = ".st.toml";
 
-/// Name of the store file, within `.git`.
+/// Name of the per-repository config file, within the git directory.
+pub(crate) const ST_REPO_CFG_FILE_NAME: &str = ".st.toml";
+
+/// Name of the store file, within the git directory.
 pub(crate) const ST_CTX_FILE_NAME: &str = ".st_store.toml";
 
+/// Name of the file that is locked while an `st` process uses the store, within the git directory.
+pub(crate) const ST_LOCK_FILE_NAME: &str = ".st_store.lock";
+
+/// Name of the pending restack plan file, within the git directory.
+pub(crate) const ST_RESTACK_FILE_NAME: &str = ".st_restack.toml";
+
+/// Name of the operation journal file, within the git directory.
+pub(crate) const ST_JOURNAL_FILE_NAME: &str = ".st_journal.toml";
+
+/// Prefix of the refs that hold named stack snapshots.
+pub(crate) const ST_SNAPSHOT_REF_PREFIX: &str = "refs/st/snapshots/";
+
+/// The ref that holds the stack tree when it is stored in git.
+pub(crate) const ST_METADATA_REF: &str = "refs/st/metadata";
+
+/// Prefix of the refs that hold the stack trees fetched from remotes.
+pub(crate) const ST_REMOTE_METADATA_REF_PREFIX: &str = "refs/st/remotes/";
+
+/// Footer of the stack overview comment that `st submit` leaves on pull requests, which is used to
+/// find the comment again.
+pub(crate) const ST_COMMENT_FOOTER: &str =
+    "_This comment was automatically generated by [`st`](https://github.com/clabby/st)._";
+
 /// Array of colors used for displaying stacks in the terminal.
 pub(crate) const COLORS: [Color; 6] = [
     Color::Blue,
@@ -2,58 +2,20 @@
 
 use super::StContext;
 use crate::{
+    constants::ST_COMMENT_FOOTER,
     errors::{StError, StResult},
     git::RepositoryExt,
+    tree::RemoteMetadata,
 };
-use git2::BranchType;
+use git2::{BranchType, Oid};
 use nu_ansi_term::Color;
-use octocrab::{models::IssueState, pulls::PullRequestHandler};
+use octocrab::{
+    models::{pulls::PullRequest, IssueState},
+    pulls::PullRequestHandler,
+    Octocrab,
+};
 
 impl<'a> StContext<'a> {
-    /// Restacks the branch onto the parent branch passed.
-    ///
-    /// Returns `true` if the branch was restacked, `false` otherwise.
-    pub fn restack_branch(&mut self, branch_name: &str, parent_name: &str) -> StResult<()> {
-        // Skip branches that do not need to be restacked.
-        if !self.needs_restack(branch_name)? {
-            println!(
-                "Branch `{}` does not need to be restacked onto `{}`.",
-                Color::Green.paint(branch_name),
-                Color::Yellow.paint(parent_name)
-            );
-            return Ok(());
-        }
-
-        // Rebase the branch onto its parent.
-        if let Err(e) = self.repository.rebase_branch_onto(branch_name, parent_name) {
-            eprintln!(
-                "Failed to rebase branch `{}` onto `{}`",
-                Color::Green.paint(branch_name),
-                Color::Yellow.paint(parent_name),
-            );
-            return Err(e.into());
-        }
-
-        // Update the parent oid cache.
-        let parent_oid = self
-            .repository
-            .find_branch(parent_name, BranchType::Local)?
-            .get()
-            .target()
-            .ok_or(StError::MissingParentOidCache)?;
-        self.tree
-            .get_mut(branch_name)
-            .ok_or_else(|| StError::BranchNotTracked(branch_name.to_string()))?
-            .parent_oid_cache = Some(parent_oid.to_string());
-
-        println!(
-            "Restacked branch `{}` onto `{}`.",
-            Color::Green.paint(branch_name),
-            Color::Yellow.paint(parent_name)
-        );
-        Ok(())
-    }
-
     /// Checks if the current working tree is clean and the stack is restacked.
     pub fn check_cleanliness(&self, branches: &[String]) -> StResult<()> {
         // Return early if the stack is not restacked or the current working tree is dirty.
@@ -160,4 +122,121 @@ impl<'a> StContext<'a> {
 
         Ok(())
     }
+
+    /// Adopts pull requests into the [StackTree](crate::tree::StackTree) once their base branch is tracked, so that each
+    /// stack is rebuilt from the bottom up.
+    ///
+    /// ## Takes
+    /// - `gh_client` - The GitHub API client.
+    /// - `remote_name` - The name of the remote to fetch the head branches from.
+    /// - `pull_requests` - The pull requests to adopt.
+    ///
+    /// ## Returns
+    /// - `Result<Vec<PullRequest>>` - The pull requests that were not adopted, as their base
+    ///   branch never became tracked.
+    pub async fn adopt_pull_requests(
+        &mut self,
+        gh_client: &Octocrab,
+        remote_name: &str,
+        mut pull_requests: Vec<PullRequest>,
+    ) -> StResult<Vec<PullRequest>> {
+        pull_requests.sort_by_key(|pr| pr.number);
+        while let Some(index) = pull_requests
+            .iter()
+            .position(|pr| self.tree.get(&pr.base.ref_field).is_some())
+        {
+            let pr = pull_requests.remove(index);
+            self.adopt_pull_request(gh_client, remote_name, &pr).await?;
+        }
+        Ok(pull_requests)
+    }
+
+    /// Tracks the head branch of a pull request on top of its base branch, creating the local
+    /// branch from the remote if needed, and links it to the pull request.
+    async fn adopt_pull_request(
+        &mut self,
+        gh_client: &Octocrab,
+        remote_name: &str,
+        pr: &PullRequest,
+    ) -> StResult<()> {
+        let (owner, repo) = self.owner_and_repository()?;
+        let branch_name = pr.head.ref_field.as_str();
+        let parent_name = pr.base.ref_field.as_str();
+
+        // Create the local branch from the remote if it does not exist yet.
+        if self
+            .repository
+            .find_branch(branch_name, BranchType::Local)
+            .is_err()
+        {
+            self.repository.fetch_branch(branch_name, remote_name)?;
+            let target = self
+                .repository
+                .find_branch(
+                    &format!("{}/{}", remote_name, branch_name),
+                    BranchType::Remote,
+                )?
+                .get()
+                .peel_to_commit()?;
+            self.repository.branch(branch_name, &target, false)?;
+        }
+
+        // Track the branch, caching its fork point with the parent.
+        if self.tree.get(branch_name).is_none() {
+            let head = |name: &str| -> StResult<Oid> {
+                self.repository
+                    .find_branch(name, BranchType::Local)?
+                    .get()
+                    .target()
+                    .ok_or(StError::BranchUnavailable)
+            };
+            let (head_oid, parent_oid) = (head(branch_name)?, head(parent_name)?);
+            let fork_point = self
+                .repository
+                .merge_base(head_oid, parent_oid)
+                .unwrap_or(parent_oid);
+            self.tree
+                .insert(parent_name, &fork_point.to_string(), branch_name)?;
+        }
+
+        // Link the branch to the pull request, along with the stack overview comment left by
+        // `st submit`, if there is one.
+        let tracked_branch = self
+            .tree
+            .get(branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(branch_name.to_string()))?;
+        if tracked_branch.remote.is_none() {
+            let page = gh_client
+                .issues(&owner, &repo)
+                .list_comments(pr.number)
+                .per_page(100)
+                .send()
+                .await?;
+            let comment_id = gh_client
+                .all_pages(page)
+                .await?
+                .into_iter()
+                .find(|c| {
+                    c.body
+                        .as_ref()
+                        .is_some_and(|b| b.contains(ST_COMMENT_FOOTER))
+                })
+                .map(|c| c.id.0);
+
+            let mut remote = RemoteMetadata::new(self.remote_name.clone(), pr.number);
+            remote.comment_id = comment_id;
+            self.tree
+                .get_mut(branch_name)
+                .ok_or_else(|| StError::BranchNotTracked(branch_name.to_string()))?
+                .remote = Some(remote);
+        }
+
+        println!(
+            "Adopted pull request #{} as branch `{}` on top of `{}`.",
+            pr.number,
+            Color::Green.paint(branch_name),
+            Color::Yellow.paint(parent_name)
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,435 @@
+//! Diagnosis and repair of the [StackTree] for [StContext], which backs `st doctor`.
+
+use super::StContext;
+use crate::{
+    errors::{StError, StResult},
+    tree::{StackTree, TrackedBranch},
+};
+use git2::{BranchType, Oid};
+use nu_ansi_term::Color;
+use std::{collections::HashSet, fmt::Display};
+
+impl StContext<'_> {
+    /// Checks the [StackTree] for broken invariants, and the parent [Oid] caches against the
+    /// object database.
+    ///
+    /// The pull requests linked to each branch are not checked, as that requires the GitHub API.
+    pub fn diagnose(&self) -> StResult<Vec<StackProblem>> {
+        let mut problems = structural_problems(&self.tree);
+
+        let mut branch_names = self.tree.branches.keys().collect::<Vec<_>>();
+        branch_names.sort();
+        for branch_name in branch_names {
+            let branch = &self.tree.branches[branch_name];
+            if !branch
+                .parent
+                .as_ref()
+                .is_some_and(|p| self.tree.branches.contains_key(p))
+            {
+                continue;
+            }
+
+            match branch.parent_oid_cache.as_deref() {
+                None => problems.push(StackProblem::MissingParentOidCache {
+                    branch: branch_name.clone(),
+                }),
+                Some(oid) => {
+                    let exists = Oid::from_str(oid)
+                        .map(|oid| self.repository.find_commit(oid).is_ok())
+                        .unwrap_or(false);
+                    if !exists {
+                        problems.push(StackProblem::UnknownParentOid {
+                            branch: branch_name.clone(),
+                            oid: oid.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(problems)
+    }
+
+    /// Applies the fix described by [StackProblem::fix_description].
+    ///
+    /// ## Returns
+    /// - `Result<bool>` - Whether the fix could be applied. A parent oid cache cannot be reset if
+    ///   the branch shares no history with its parent, in which case the tree is left untouched.
+    pub fn fix_problem(&mut self, problem: &StackProblem) -> StResult<bool> {
+        match problem {
+            StackProblem::MissingTrunk => {
+                let trunk_name = self.tree.trunk_name.clone();
+                self.tree.branches.insert(
+                    trunk_name.clone(),
+                    TrackedBranch::new(trunk_name, None, None),
+                );
+            }
+            StackProblem::MissingParent { branch, .. } | StackProblem::Cycle { branch } => {
+                // Unlink the branch from its current parent, if it is tracked.
+                let old_parent = self.tracked_branch(branch)?.parent.clone();
+                if let Some(parent) = old_parent.and_then(|p| self.tree.get_mut(&p)) {
+                    parent.children.remove(branch);
+                }
+
+                let trunk_name = self.tree.trunk_name.clone();
+                let parent_oid_cache = self.fork_point_with(branch, &trunk_name)?;
+                let tracked_branch = self
+                    .tree
+                    .get_mut(branch)
+                    .ok_or_else(|| StError::BranchNotTracked(branch.clone()))?;
+                tracked_branch.parent = Some(trunk_name.clone());
+                tracked_branch.parent_oid_cache = parent_oid_cache;
+                self.tracked_branch_mut(&trunk_name)?
+                    .children
+                    .insert(branch.clone());
+            }
+            StackProblem::MissingChildLink { parent, child } => {
+                self.tracked_branch_mut(parent)?
+                    .children
+                    .insert(child.clone());
+            }
+            StackProblem::DanglingChild { parent, child } => {
+                self.tracked_branch_mut(parent)?.children.remove(child);
+            }
+            StackProblem::MissingParentOidCache { branch }
+            | StackProblem::UnknownParentOid { branch, .. } => {
+                let parent = self
+                    .tracked_branch(branch)?
+                    .parent
+                    .clone()
+                    .ok_or(StError::CannotModifyTrunkBranch)?;
+                let Some(parent_oid_cache) = self.fork_point_with(branch, &parent)? else {
+                    return Ok(false);
+                };
+                self.tracked_branch_mut(branch)?.parent_oid_cache = Some(parent_oid_cache);
+            }
+            StackProblem::PullRequestMismatch { branch, .. } => {
+                self.tracked_branch_mut(branch)?.remote = None;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns the merge-base of a branch and another branch, in string form, or [None] if they
+    /// share no history.
+    fn fork_point_with(&self, branch_name: &str, parent_name: &str) -> StResult<Option<String>> {
+        let head = |name: &str| -> StResult<Oid> {
+            self.repository
+                .find_branch(name, BranchType::Local)?
+                .get()
+                .target()
+                .ok_or(StError::BranchUnavailable)
+        };
+        Ok(self
+            .repository
+            .merge_base(head(branch_name)?, head(parent_name)?)
+            .ok()
+            .map(|oid| oid.to_string()))
+    }
+
+    /// Returns the [TrackedBranch] with the given name.
+    fn tracked_branch(&self, branch_name: &str) -> StResult<&TrackedBranch> {
+        self.tree
+            .get(branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(branch_name.to_string()))
+    }
+
+    /// Returns the [TrackedBranch] with the given name, mutably.
+    fn tracked_branch_mut(&mut self, branch_name: &str) -> StResult<&mut TrackedBranch> {
+        self.tree
+            .get_mut(branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(branch_name.to_string()))
+    }
+}
+
+/// Checks the links between the branches of a [StackTree].
+fn structural_problems(tree: &StackTree) -> Vec<StackProblem> {
+    let mut problems = Vec::new();
+    if !tree.branches.contains_key(&tree.trunk_name) {
+        problems.push(StackProblem::MissingTrunk);
+    }
+
+    let mut branch_names = tree.branches.keys().collect::<Vec<_>>();
+    branch_names.sort();
+
+    // Every branch other than trunk must sit on top of a tracked parent.
+    for branch_name in branch_names.iter() {
+        let branch = &tree.branches[*branch_name];
+        if **branch_name == tree.trunk_name {
+            continue;
+        }
+        if !branch
+            .parent
+            .as_ref()
+            .is_some_and(|p| tree.branches.contains_key(p))
+        {
+            problems.push(StackProblem::MissingParent {
+                branch: branch_name.to_string(),
+                parent: branch.parent.clone(),
+            });
+        }
+    }
+
+    // Parent and children links must agree with each other.
+    for branch_name in branch_names.iter() {
+        let branch = &tree.branches[*branch_name];
+        let mut children = branch.children.iter().collect::<Vec<_>>();
+        children.sort();
+        for child in children {
+            if tree.get(child).and_then(|c| c.parent.as_ref()) != Some(*branch_name) {
+                problems.push(StackProblem::DanglingChild {
+                    parent: branch_name.to_string(),
+                    child: child.clone(),
+                });
+            }
+        }
+
+        if let Some(parent) = branch.parent.as_ref().and_then(|p| tree.get(p)) {
+            if !parent.children.contains(*branch_name) {
+                problems.push(StackProblem::MissingChildLink {
+                    parent: parent.name.clone(),
+                    child: branch_name.to_string(),
+                });
+            }
+        }
+    }
+
+    // Following the parents of any branch must lead to trunk. Each cycle is reported once, for
+    // the first of its branches by name.
+    let mut reported = HashSet::new();
+    for branch_name in branch_names.iter() {
+        let mut path: Vec<String> = Vec::new();
+        let mut current = Some(branch_name.to_string());
+        while let Some(name) = current.take() {
+            if name == tree.trunk_name {
+                break;
+            }
+            if let Some(start) = path.iter().position(|n| *n == name) {
+                let cycle = &path[start..];
+                if cycle.iter().all(|n| !reported.contains(n)) {
+                    if let Some(first) = cycle.iter().min() {
+                        problems.push(StackProblem::Cycle {
+                            branch: first.clone(),
+                        });
+                    }
+                    reported.extend(cycle.iter().cloned());
+                }
+                break;
+            }
+            current = tree.get(&name).and_then(|b| b.parent.clone());
+            path.push(name);
+        }
+    }
+
+    problems
+}
+
+/// A broken invariant of the [StackTree].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum StackProblem {
+    /// The trunk branch is not tracked.
+    MissingTrunk,
+    /// A branch other than trunk has no parent, or its parent is not tracked.
+    MissingParent {
+        /// The branch without a tracked parent.
+        branch: String,
+        /// The parent that is not tracked, if any.
+        parent: Option<String>,
+    },
+    /// Following the parents of a branch leads back to the branch itself.
+    Cycle {
+        /// A branch within the cycle.
+        branch: String,
+    },
+    /// A branch is not listed as a child of its parent.
+    MissingChildLink {
+        /// The parent branch.
+        parent: String,
+        /// The branch that is missing from the parent's children.
+        child: String,
+    },
+    /// A branch lists a child that is not tracked, or that sits on top of another branch.
+    DanglingChild {
+        /// The parent branch.
+        parent: String,
+        /// The child that does not point back at the parent.
+        child: String,
+    },
+    /// A branch has no parent [Oid] cache.
+    MissingParentOidCache {
+        /// The branch without a parent [Oid] cache.
+        branch: String,
+    },
+    /// The parent [Oid] cache of a branch is not a commit in the repository.
+    UnknownParentOid {
+        /// The branch with the unknown parent [Oid] cache.
+        branch: String,
+        /// The cached [Oid], in string form.
+        oid: String,
+    },
+    /// The pull request linked to a branch does not exist, or belongs to another branch.
+    PullRequestMismatch {
+        /// The branch linked to the pull request.
+        branch: String,
+        /// The number of the linked pull request.
+        pr_number: u64,
+        /// The head branch of the pull request. [None] if the pull request does not exist.
+        head: Option<String>,
+    },
+}
+
+impl StackProblem {
+    /// Returns a description of the fix applied by [StContext::fix_problem], phrased as a
+    /// question.
+    pub fn fix_description(&self, trunk_name: &str) -> String {
+        match self {
+            Self::MissingTrunk => format!("Track `{}` as trunk?", Color::Yellow.paint(trunk_name)),
+            Self::MissingParent { branch, .. } | Self::Cycle { branch } => format!(
+                "Move `{}` on top of `{}`?",
+                Color::Green.paint(branch),
+                Color::Yellow.paint(trunk_name)
+            ),
+            Self::MissingChildLink { parent, child } => format!(
+                "Add `{}` to the children of `{}`?",
+                Color::Green.paint(child),
+                Color::Yellow.paint(parent)
+            ),
+            Self::DanglingChild { parent, child } => format!(
+                "Remove `{}` from the children of `{}`?",
+                Color::Green.paint(child),
+                Color::Yellow.paint(parent)
+            ),
+            Self::MissingParentOidCache { branch } | Self::UnknownParentOid { branch, .. } => {
+                format!(
+                    "Reset the parent oid cache of `{}` to its fork point?",
+                    Color::Green.paint(branch)
+                )
+            }
+            Self::PullRequestMismatch {
+                branch, pr_number, ..
+            } => format!(
+                "Unlink pull request #{} from `{}`?",
+                pr_number,
+                Color::Green.paint(branch)
+            ),
+        }
+    }
+}
+
+impl Display for StackProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingTrunk => write!(f, "The trunk branch is not tracked."),
+            Self::MissingParent { branch, parent } => match parent {
+                Some(parent) => write!(
+                    f,
+                    "Branch `{}` sits on top of `{}`, which is not tracked.",
+                    Color::Green.paint(branch),
+                    Color::Yellow.paint(parent)
+                ),
+                None => write!(f, "Branch `{}` has no parent.", Color::Green.paint(branch)),
+            },
+            Self::Cycle { branch } => write!(
+                f,
+                "The parents of branch `{}` form a cycle that never reaches trunk.",
+                Color::Green.paint(branch)
+            ),
+            Self::MissingChildLink { parent, child } => write!(
+                f,
+                "Branch `{}` sits on top of `{}`, but is not listed as one of its children.",
+                Color::Green.paint(child),
+                Color::Yellow.paint(parent)
+            ),
+            Self::DanglingChild { parent, child } => write!(
+                f,
+                "Branch `{}` lists `{}` as a child, but `{}` does not sit on top of it.",
+                Color::Yellow.paint(parent),
+                Color::Green.paint(child),
+                Color::Green.paint(child)
+            ),
+            Self::MissingParentOidCache { branch } => write!(
+                f,
+                "Branch `{}` has no parent oid cache, so it cannot be restacked.",
+                Color::Green.paint(branch)
+            ),
+            Self::UnknownParentOid { branch, oid } => write!(
+                f,
+                "The parent oid cache of branch `{}` points at `{}`, which is not in the repository.",
+                Color::Green.paint(branch),
+                &oid[..7.min(oid.len())]
+            ),
+            Self::PullRequestMismatch {
+                branch,
+                pr_number,
+                head,
+            } => match head {
+                Some(head) => write!(
+                    f,
+                    "Branch `{}` is linked to pull request #{}, which belongs to branch `{}`.",
+                    Color::Green.paint(branch),
+                    pr_number,
+                    Color::Yellow.paint(head)
+                ),
+                None => write!(
+                    f,
+                    "Branch `{}` is linked to pull request #{}, which does not exist.",
+                    Color::Green.paint(branch),
+                    pr_number
+                ),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{structural_problems, StackProblem};
+    use crate::{test_utils::TestRepo, tree::StackTree};
+
+    #[test]
+    fn detects_broken_links_and_cycles() {
+        let mut tree = StackTree::new("main".to_string());
+        tree.insert("main", "0", "a").unwrap();
+        tree.insert("a", "1", "b").unwrap();
+        assert!(structural_problems(&tree).is_empty());
+
+        // Point `a` at `b`, forming a cycle, and leave a dangling child on trunk.
+        tree.get_mut("a").unwrap().parent = Some("b".to_string());
+        tree.get_mut("b").unwrap().children.insert("a".to_string());
+        tree.get_mut("main")
+            .unwrap()
+            .children
+            .insert("c".to_string());
+
+        let problems = structural_problems(&tree);
+        assert!(problems.contains(&StackProblem::Cycle {
+            branch: "a".to_string()
+        }));
+        assert!(problems.contains(&StackProblem::DanglingChild {
+            parent: "main".to_string(),
+            child: "a".to_string()
+        }));
+        assert!(problems.contains(&StackProblem::DanglingChild {
+            parent: "main".to_string(),
+            child: "c".to_string()
+        }));
+    }
+
+    #[test]
+    fn unrelated_history_is_left_unfixed() {
+        let repo = TestRepo::new();
+        let mut ctx = repo.ctx();
+
+        // Track a branch that shares no history with trunk, without a parent oid cache.
+        repo.git(&["checkout", "-q", "--orphan", "a"]);
+        repo.commit("a.txt", "a\n", "a");
+        ctx.tree.insert("main", "0", "a").unwrap();
+        ctx.tree.get_mut("a").unwrap().parent_oid_cache = None;
+
+        let problem = StackProblem::MissingParentOidCache {
+            branch: "a".to_string(),
+        };
+        assert_eq!(ctx.diagnose().unwrap(), vec![problem.clone()]);
+        assert!(!ctx.fix_problem(&problem).unwrap());
+        assert_eq!(ctx.diagnose().unwrap(), vec![problem]);
+    }
+}
@@ -10,7 +10,10 @@ use crate::{
     git::RepositoryExt,
 };
 use nu_ansi_term::Color;
-use std::fmt::{Display, Write};
+use std::{
+    fmt::{Display, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 impl StContext<'_> {
     /// Gathers an in-order list of [DisplayBranch]es, containing the log-line and branch name.
@@ -163,3 +166,18 @@ impl Display for DisplayBranch {
         write!(f, "{}", self.display_value)
     }
 }
+
+/// Formats the time elapsed since the passed Unix timestamp, in seconds, as a short relative
+/// time (e.g. `5m ago`).
+pub(crate) fn format_time_ago(timestamp: u64) -> String {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs().saturating_sub(timestamp))
+        .unwrap_or_default();
+    match elapsed {
+        0..60 => format!("{}s ago", elapsed),
+        60..3600 => format!("{}m ago", elapsed / 60),
+        3600..86400 => format!("{}h ago", elapsed / 3600),
+        _ => format!("{}d ago", elapsed / 86400),
+    }
+}
@@ -0,0 +1,410 @@
+//! Operation journal for [StContext], which backs `st undo` and `st redo`.
+
+use super::StContext;
+use crate::{
+    ctx::{
+        format_time_ago, journal_path,
+        store::{decode_versioned, encode_versioned},
+        write_atomic,
+    },
+    errors::{StError, StResult},
+    git::RepositoryExt,
+    tree::StackTree,
+};
+use git2::{BranchType, Oid, Repository};
+use nu_ansi_term::Color;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The maximum number of operations retained in the [Journal].
+const MAX_JOURNAL_ENTRIES: usize = 100;
+
+/// The maximum size of the serialized [Journal], in bytes. Every entry holds the [StackTree]
+/// twice, so the oldest entries are dropped once large trees would exceed it.
+const MAX_JOURNAL_BYTES: usize = 1 << 20;
+
+impl StContext<'_> {
+    /// Stops the current invocation from being recorded in the [Journal].
+    pub fn disable_journal(&mut self) {
+        self.snapshot = None;
+    }
+
+    /// Records the changes made to the [StackTree] and local branch refs since the [StContext]
+    /// was loaded as a new operation in the [Journal]. Does nothing if nothing changed.
+    pub(crate) fn record_operation(&self) -> StResult<()> {
+        let Some(snapshot) = self.snapshot.as_ref() else {
+            return Ok(());
+        };
+
+        let heads = local_branch_heads(self.repository)?;
+        let refs = snapshot
+            .heads
+            .keys()
+            .chain(heads.keys())
+            .filter(|name| snapshot.heads.get(*name) != heads.get(*name))
+            .map(|name| {
+                (
+                    name.clone(),
+                    RefChange {
+                        before: snapshot.heads.get(name).cloned(),
+                        after: heads.get(name).cloned(),
+                    },
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+        if refs.is_empty() && snapshot.tree == self.tree {
+            return Ok(());
+        }
+
+        let mut journal = Journal::load(self.repository)?;
+        journal.entries.push(JournalEntry {
+            command: std::env::args().skip(1).collect::<Vec<_>>().join(" "),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            before: snapshot.tree.clone(),
+            after: self.tree.clone(),
+            refs,
+        });
+        if journal.entries.len() > MAX_JOURNAL_ENTRIES {
+            journal.entries.remove(0);
+        }
+        journal.redo.clear();
+        journal.save(self.repository)
+    }
+
+    /// Reverts the most recent operation in the [Journal], restoring the [StackTree] and every
+    /// branch ref it moved to their state before the operation.
+    ///
+    /// Returns the reverted [JournalEntry] as [JournalOutcome::Applied].
+    pub fn undo_operation(&mut self) -> StResult<JournalOutcome> {
+        let mut journal = Journal::load(self.repository)?;
+        let Some(entry) = journal.entries.pop() else {
+            return Ok(JournalOutcome::Empty);
+        };
+
+        let refs = entry.refs.iter().map(|(name, change)| {
+            (
+                name.as_str(),
+                change.after.as_deref(),
+                change.before.as_deref(),
+            )
+        });
+        if !self.restore_refs(refs, "st: undo")? {
+            return Ok(JournalOutcome::Aborted);
+        }
+        self.tree = entry.before.clone();
+
+        journal.redo.push(entry.clone());
+        journal.save(self.repository)?;
+        Ok(JournalOutcome::Applied(entry))
+    }
+
+    /// Re-applies the most recently undone operation in the [Journal], restoring the [StackTree]
+    /// and every branch ref it moved to their state after the operation.
+    ///
+    /// Returns the re-applied [JournalEntry] as [JournalOutcome::Applied].
+    pub fn redo_operation(&mut self) -> StResult<JournalOutcome> {
+        let mut journal = Journal::load(self.repository)?;
+        let Some(entry) = journal.redo.pop() else {
+            return Ok(JournalOutcome::Empty);
+        };
+
+        let refs = entry.refs.iter().map(|(name, change)| {
+            (
+                name.as_str(),
+                change.before.as_deref(),
+                change.after.as_deref(),
+            )
+        });
+        if !self.restore_refs(refs, "st: redo")? {
+            return Ok(JournalOutcome::Aborted);
+        }
+        self.tree = entry.after.clone();
+
+        journal.entries.push(entry.clone());
+        journal.save(self.repository)?;
+        Ok(JournalOutcome::Applied(entry))
+    }
+
+    /// Points each branch at its target commit, deleting branches whose target is [None], and
+    /// refreshes the working tree.
+    ///
+    /// The passed refs are `(branch, expected, target)` tuples. If a branch no longer points at
+    /// its expected commit, the user is asked to confirm before it is overwritten.
+    ///
+    /// Returns `false` if the user declined to overwrite a branch.
+    fn restore_refs<'r>(
+        &self,
+        refs: impl Iterator<Item = (&'r str, Option<&'r str>, Option<&'r str>)> + Clone,
+        log_message: &str,
+    ) -> StResult<bool> {
+        if !self.repository.is_working_tree_clean()? {
+            return Err(StError::WorkingTreeDirty);
+        }
+
+        // Ask for confirmation before overwriting branches that have moved since the operation.
+        let heads = local_branch_heads(self.repository)?;
+        for (name, expected, _) in refs.clone() {
+            if heads.get(name).map(String::as_str) == expected {
+                continue;
+            }
+
+            let confirm = inquire::Confirm::new(
+                format!(
+                    "Branch `{}` has changed since the operation. Overwrite it anyway?",
+                    Color::Green.paint(name)
+                )
+                .as_str(),
+            )
+            .with_default(false)
+            .prompt()?;
+            if !confirm {
+                return Ok(false);
+            }
+        }
+
+        let current_branch = self.repository.current_branch_name().ok();
+        for (name, _, target) in refs {
+            match target {
+                Some(oid) => {
+                    self.repository.reference(
+                        format!("refs/heads/{}", name).as_str(),
+                        Oid::from_str(oid)?,
+                        true,
+                        log_message,
+                    )?;
+                }
+                None => {
+                    let Ok(mut branch) = self.repository.find_branch(name, BranchType::Local)
+                    else {
+                        continue;
+                    };
+
+                    // Move off of the branch before deleting it.
+                    if current_branch.as_deref() == Some(name) {
+                        self.repository
+                            .set_head(format!("refs/heads/{}", self.tree.trunk_name).as_str())?;
+                    }
+                    branch.delete()?;
+                }
+            }
+        }
+
+        // Refresh the working tree to match the restored branch.
+        let checked_out = match self.repository.current_branch_name() {
+            Ok(name) => name,
+            Err(_) => self.tree.trunk_name.clone(),
+        };
+        self.repository.force_checkout_branch(&checked_out)?;
+        Ok(true)
+    }
+}
+
+/// Returns a map of every local branch name to the [Oid] of its `HEAD` commit, in string form.
+pub(crate) fn local_branch_heads(repository: &Repository) -> StResult<BTreeMap<String, String>> {
+    let mut heads = BTreeMap::new();
+    for branch in repository.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        if let (Some(name), Some(target)) = (branch.name()?, branch.get().target()) {
+            heads.insert(name.to_string(), target.to_string());
+        }
+    }
+    Ok(heads)
+}
+
+/// The state of the [StackTree] and local branch refs when a [StContext] was loaded.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OperationSnapshot {
+    /// The [StackTree] when the [StContext] was loaded.
+    pub tree: StackTree,
+    /// The local branch heads when the [StContext] was loaded.
+    pub heads: BTreeMap<String, String>,
+}
+
+impl OperationSnapshot {
+    /// Captures the current state of the passed [StackTree] and the local branch refs.
+    pub fn capture(repository: &Repository, tree: &StackTree) -> StResult<Self> {
+        Ok(Self {
+            tree: tree.clone(),
+            heads: local_branch_heads(repository)?,
+        })
+    }
+}
+
+/// A journal of the operations performed by `st`, persisted within `.git`.
+#[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Journal {
+    /// The operations that have been performed, oldest first.
+    #[serde(default)]
+    pub entries: Vec<JournalEntry>,
+    /// The operations that have been undone, most recently undone last.
+    #[serde(default)]
+    pub redo: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// Loads the [Journal] for the given [Repository], or an empty one if it does not exist.
+    pub fn load(repository: &Repository) -> StResult<Self> {
+        let journal_path = journal_path(repository);
+        if !journal_path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(decode_versioned(&std::fs::read_to_string(journal_path)?)?.0)
+    }
+
+    /// Persists the [Journal] for the given [Repository], dropping the oldest operations until
+    /// it fits within [MAX_JOURNAL_BYTES].
+    pub fn save(mut self, repository: &Repository) -> StResult<()> {
+        let mut contents = encode_versioned(&self)?;
+        if contents.len() > MAX_JOURNAL_BYTES {
+            // Drop the oldest operations by their own serialized size, rather than serializing
+            // the whole journal again after each one.
+            let mut excess = contents.len() - MAX_JOURNAL_BYTES;
+            while excess > 0 && !(self.entries.is_empty() && self.redo.is_empty()) {
+                let dropped = if self.entries.is_empty() {
+                    self.redo.remove(0)
+                } else {
+                    self.entries.remove(0)
+                };
+                excess = excess.saturating_sub(toml::to_string(&dropped)?.len());
+            }
+            contents = encode_versioned(&self)?;
+        }
+
+        let journal_path = journal_path(repository);
+        write_atomic(&journal_path, &contents)
+    }
+}
+
+/// The outcome of undoing or redoing an operation in the [Journal].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum JournalOutcome {
+    /// The operation was reverted or re-applied.
+    Applied(JournalEntry),
+    /// There is no operation to revert or re-apply.
+    Empty,
+    /// The user declined to overwrite a branch that moved since the operation, so nothing was
+    /// changed.
+    Aborted,
+}
+
+/// A single operation recorded in the [Journal].
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct JournalEntry {
+    /// The arguments that `st` was invoked with.
+    pub command: String,
+    /// The time that the operation finished, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The [StackTree] before the operation.
+    pub before: StackTree,
+    /// The [StackTree] after the operation.
+    pub after: StackTree,
+    /// The local branch refs moved by the operation.
+    pub refs: BTreeMap<String, RefChange>,
+}
+
+/// A local branch ref moved by an operation.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RefChange {
+    /// The [Oid] of the branch before the operation, in string form. [None] if it was created.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    /// The [Oid] of the branch after the operation, in string form. [None] if it was deleted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+}
+
+impl Display for JournalEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` {}",
+            Color::Blue.paint(format!("st {}", self.command)),
+            Color::DarkGray.paint(format!("({})", format_time_ago(self.timestamp)))
+        )?;
+        for (name, change) in self.refs.iter() {
+            let short = |oid: &Option<String>| {
+                oid.as_deref()
+                    .map_or("none".to_string(), |o| o[..7.min(o.len())].to_string())
+            };
+            write!(
+                f,
+                "\n   {} {} -> {}",
+                Color::Green.paint(name),
+                short(&change.before),
+                short(&change.after)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Journal, JournalEntry, JournalOutcome, MAX_JOURNAL_BYTES};
+    use crate::{ctx::journal_path, test_utils::TestRepo, tree::StackTree};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn undo_and_redo_restore_refs_and_tree() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        let a_head = test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        ctx.save().unwrap();
+
+        let JournalOutcome::Applied(entry) = ctx.undo_operation().unwrap() else {
+            panic!("The operation must be undone.");
+        };
+        assert_eq!(entry.refs["a"].after, Some(a_head.to_string()));
+        assert!(test_repo
+            .repo
+            .find_branch("a", git2::BranchType::Local)
+            .is_err());
+        assert!(ctx.tree.get("a").is_none());
+        assert_eq!(ctx.undo_operation().unwrap(), JournalOutcome::Empty);
+
+        assert!(matches!(
+            ctx.redo_operation().unwrap(),
+            JournalOutcome::Applied(_)
+        ));
+        assert_eq!(test_repo.head("a"), a_head);
+        assert!(ctx.tree.get("a").is_some());
+        assert_eq!(ctx.redo_operation().unwrap(), JournalOutcome::Empty);
+    }
+
+    #[test]
+    fn journal_is_capped_by_size() {
+        let test_repo = TestRepo::new();
+        let tree = StackTree::new("main".to_string());
+        let journal = Journal {
+            entries: (0..40)
+                .map(|timestamp| JournalEntry {
+                    command: "x".repeat(1 << 16),
+                    timestamp,
+                    before: tree.clone(),
+                    after: tree.clone(),
+                    refs: BTreeMap::new(),
+                })
+                .collect(),
+            redo: Vec::new(),
+        };
+        journal.save(&test_repo.repo).unwrap();
+
+        let size = std::fs::metadata(journal_path(&test_repo.repo))
+            .unwrap()
+            .len();
+        assert!(size as usize <= MAX_JOURNAL_BYTES);
+        let journal = Journal::load(&test_repo.repo).unwrap();
+        assert!(!journal.entries.is_empty() && journal.entries.len() < 40);
+        assert_eq!(journal.entries.last().unwrap().timestamp, 39);
+    }
+}
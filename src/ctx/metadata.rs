@@ -0,0 +1,343 @@
+//! Storage of the [StackTree] in git refs for [StContext], which allows the stack to be exchanged
+//! with remotes through `st metadata push` and `st metadata pull`.
+
+use super::{
+    store::{decode_versioned, encode_versioned},
+    StContext,
+};
+use crate::{
+    constants::{ST_METADATA_REF, ST_REMOTE_METADATA_REF_PREFIX},
+    errors::{StError, StResult},
+    git::RepositoryExt,
+    tree::StackTree,
+};
+use git2::{BranchType, Commit, Oid, Repository, Signature};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// The name of the file holding the serialized stack within an `st` commit's tree.
+const STACK_FILE_NAME: &str = "stack.toml";
+
+impl StContext<'_> {
+    /// Commits the [StackTree] to `refs/st/metadata`, unless it is unchanged since the latest
+    /// metadata commit.
+    ///
+    /// Branches in the latest metadata commit that were pruned from the [StackTree] because they
+    /// have no local branch are carried over, so that only branches deleted or untracked
+    /// explicitly are removed from the metadata.
+    ///
+    /// ## Returns
+    /// - `Result<Oid>` - The latest metadata commit.
+    pub(crate) fn write_metadata_ref(&self) -> StResult<Oid> {
+        let parent = self
+            .repository
+            .find_reference(ST_METADATA_REF)
+            .and_then(|r| r.peel_to_commit())
+            .ok();
+        let parent_stack = parent
+            .as_ref()
+            .map(|parent| read_stack::<StackTree>(self.repository, parent))
+            .transpose()?;
+
+        let mut stack = self.tree.clone();
+        if let Some(parent_stack) = parent_stack.as_ref() {
+            let pruned = parent_stack
+                .branches
+                .values()
+                .filter(|b| {
+                    !stack.branches.contains_key(&b.name)
+                        && !self.tracked_on_load.contains(&b.name)
+                        && self
+                            .repository
+                            .find_branch(&b.name, BranchType::Local)
+                            .is_err()
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            if !pruned.is_empty() {
+                stack
+                    .branches
+                    .extend(pruned.into_iter().map(|b| (b.name.clone(), b)));
+                stack.relink();
+            }
+
+            if parent_stack == &stack {
+                return Ok(parent.as_ref().expect("Parent stack was read").id());
+            }
+        }
+
+        let commit = commit_stack(
+            self.repository,
+            &stack,
+            "st: update metadata",
+            &parent.iter().collect::<Vec<_>>(),
+        )?;
+        self.repository
+            .reference(ST_METADATA_REF, commit, true, "st: update metadata")?;
+        Ok(commit)
+    }
+
+    /// Fetches the [StackTree] from a remote and merges it into the local one, branch by branch.
+    ///
+    /// Tracked branches that only exist on the remote are fetched and created locally. Tracked
+    /// branches that were never pushed to the remote are kept in the metadata, but are left out
+    /// of the local [StackTree].
+    ///
+    /// ## Takes
+    /// - `remote_name` - The name of the remote to pull from.
+    pub fn pull_metadata(&mut self, remote_name: &str) -> StResult<MetadataSync> {
+        let local = self.write_metadata_ref()?;
+        let Some(remote) = self.fetch_metadata(remote_name)? else {
+            return Ok(MetadataSync::Missing);
+        };
+
+        if local == remote || self.repository.graph_descendant_of(local, remote)? {
+            return Ok(MetadataSync::UpToDate);
+        }
+
+        let (head, conflicts) = if self.repository.graph_descendant_of(remote, local)? {
+            (remote, Vec::new())
+        } else {
+            // The stacks diverged. Merge them relative to their latest common state, or to an
+            // empty stack if they were started independently.
+            let base = match self.repository.merge_base(local, remote) {
+                Ok(base) => read_stack(self.repository, &self.repository.find_commit(base)?)?,
+                Err(_) => StackTree::new(self.tree.trunk_name.clone()),
+            };
+            let local_commit = self.repository.find_commit(local)?;
+            let remote_commit = self.repository.find_commit(remote)?;
+            let (merged, conflicts) = StackTree::merge(
+                &base,
+                &read_stack(self.repository, &local_commit)?,
+                &read_stack(self.repository, &remote_commit)?,
+            )?;
+            let commit = commit_stack(
+                self.repository,
+                &merged,
+                &format!("st: merge metadata from {}", remote_name),
+                &[&local_commit, &remote_commit],
+            )?;
+            (commit, conflicts)
+        };
+        self.repository
+            .reference(ST_METADATA_REF, head, true, "st: pull metadata")?;
+        self.tree = read_stack(self.repository, &self.repository.find_commit(head)?)?;
+
+        // Create the branches that are only available on the remote.
+        let (mut created, mut remote_only) = (Vec::new(), Vec::new());
+        for branch_name in self.tree.branches()? {
+            if self
+                .repository
+                .find_branch(&branch_name, BranchType::Local)
+                .is_ok()
+            {
+                continue;
+            }
+            if self
+                .repository
+                .fetch_branch(&branch_name, remote_name)
+                .is_err()
+            {
+                remote_only.push(branch_name);
+                continue;
+            }
+            let remote_branch = self.repository.find_branch(
+                &format!("{}/{}", remote_name, branch_name),
+                BranchType::Remote,
+            )?;
+            let target = remote_branch.get().peel_to_commit()?;
+            self.repository.branch(&branch_name, &target, false)?;
+            created.push(branch_name);
+        }
+        self.prune()?;
+
+        Ok(MetadataSync::Updated {
+            conflicts,
+            created,
+            remote_only,
+        })
+    }
+
+    /// Merges the [StackTree] from a remote into the local one, and pushes the result back to
+    /// the remote.
+    ///
+    /// ## Takes
+    /// - `remote_name` - The name of the remote to push to.
+    pub fn push_metadata(&mut self, remote_name: &str) -> StResult<MetadataSync> {
+        let sync = self.pull_metadata(remote_name)?;
+        self.write_metadata_ref()?;
+        self.repository.push_refspec(
+            &format!("{}:{}", ST_METADATA_REF, ST_METADATA_REF),
+            remote_name,
+        )?;
+        Ok(sync)
+    }
+
+    /// Fetches the metadata ref of a remote into `refs/st/remotes/<remote>/metadata`.
+    ///
+    /// ## Returns
+    /// - `Result<Some(Oid)>` - The metadata commit of the remote.
+    /// - `Result<None>` - If the remote has no metadata.
+    fn fetch_metadata(&self, remote_name: &str) -> StResult<Option<Oid>> {
+        if self
+            .repository
+            .remote_ref(ST_METADATA_REF, remote_name)?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        let tracking_ref = format!("{}{}/metadata", ST_REMOTE_METADATA_REF_PREFIX, remote_name);
+        self.repository.fetch_refspec(
+            &format!("+{}:{}", ST_METADATA_REF, tracking_ref),
+            remote_name,
+        )?;
+        Ok(Some(self.repository.refname_to_id(&tracking_ref)?))
+    }
+}
+
+/// Reads the [StackTree] from `refs/st/metadata`.
+///
+/// ## Returns
+/// - `Result<Some(StackTree)>` - The stack tree in the latest metadata commit.
+/// - `Result<None>` - If the repository has no metadata ref.
+pub(super) fn read_metadata_ref(repository: &Repository) -> StResult<Option<StackTree>> {
+    let Ok(reference) = repository.find_reference(ST_METADATA_REF) else {
+        return Ok(None);
+    };
+    read_stack(repository, &reference.peel_to_commit()?).map(Some)
+}
+
+/// Writes a commit whose tree holds the passed value, serialized as TOML.
+///
+/// ## Takes
+/// - `repository` - The repository to write the commit to.
+/// - `stack` - The value to serialize into the commit.
+/// - `message` - The commit message.
+/// - `parents` - The parents of the commit.
+///
+/// ## Returns
+/// - `Result<Oid>` - The new commit. No reference is updated.
+pub(super) fn commit_stack<T: Serialize>(
+    repository: &Repository,
+    stack: &T,
+    message: &str,
+    parents: &[&Commit<'_>],
+) -> StResult<Oid> {
+    let blob = repository.blob(encode_versioned(stack)?.as_bytes())?;
+    let mut builder = repository.treebuilder(None)?;
+    builder.insert(STACK_FILE_NAME, blob, 0o100644)?;
+    let tree = repository.find_tree(builder.write()?)?;
+
+    let signature = repository
+        .signature()
+        .or_else(|_| Signature::now("st", "st@localhost"))?;
+    Ok(repository.commit(None, &signature, &signature, message, &tree, parents)?)
+}
+
+/// Reads the value stored within a commit written by [commit_stack].
+pub(super) fn read_stack<T: DeserializeOwned>(
+    repository: &Repository,
+    commit: &Commit<'_>,
+) -> StResult<T> {
+    let tree = commit.tree()?;
+    let entry = tree.get_name(STACK_FILE_NAME).ok_or_else(|| {
+        StError::DecodingError(format!("Commit `{}` does not hold a stack.", commit.id()))
+    })?;
+    let blob = repository.find_blob(entry.id())?;
+    let contents =
+        std::str::from_utf8(blob.content()).map_err(|e| StError::DecodingError(e.to_string()))?;
+    Ok(decode_versioned(contents)?.0)
+}
+
+/// The result of exchanging the [StackTree] with a remote.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum MetadataSync {
+    /// The remote has no metadata.
+    Missing,
+    /// The remote metadata was already merged into the local metadata.
+    UpToDate,
+    /// The remote metadata was merged into the local metadata.
+    Updated {
+        /// The branches that were changed differently on both sides. The local version was kept.
+        conflicts: Vec<String>,
+        /// The branches that were fetched from the remote and created locally.
+        created: Vec<String>,
+        /// The branches that could not be fetched from the remote. They are kept in the
+        /// metadata, but are not tracked locally.
+        remote_only: Vec<String>,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_metadata_ref, MetadataSync};
+    use crate::test_utils::TestRepo;
+    use git2::Repository;
+
+    #[test]
+    fn fetch_metadata_detects_missing_ref() {
+        let test_repo = TestRepo::new();
+        let _remote = test_repo.add_remote();
+        let mut ctx = test_repo.ctx();
+        assert_eq!(ctx.fetch_metadata("origin").unwrap(), None);
+
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        ctx.push_metadata("origin").unwrap();
+        let local = ctx.write_metadata_ref().unwrap();
+        assert_eq!(ctx.fetch_metadata("origin").unwrap(), Some(local));
+    }
+
+    #[test]
+    fn pull_keeps_branches_that_cannot_be_fetched() {
+        let ours = TestRepo::new();
+        let remote = ours.add_remote();
+        let mut ours_ctx = ours.ctx();
+        ours.create_branch(&mut ours_ctx, "a", "a.txt", "a\n");
+        ours.create_branch(&mut ours_ctx, "b", "b.txt", "b\n");
+        ours.git(&["push", "-q", "origin", "a"]);
+        ours_ctx.push_metadata("origin").unwrap();
+
+        // They only have access to `a`, as `b` was never pushed.
+        let theirs = TestRepo::cloned(remote.path());
+        let mut theirs_ctx = theirs.ctx();
+        let sync = theirs_ctx.pull_metadata("origin").unwrap();
+        assert_eq!(
+            sync,
+            MetadataSync::Updated {
+                conflicts: vec![],
+                created: vec!["a".to_string()],
+                remote_only: vec!["b".to_string()],
+            }
+        );
+        assert!(theirs_ctx.tree.get("a").is_some());
+        assert!(theirs_ctx.tree.get("b").is_none());
+
+        // Pushing their changes keeps `b` in the shared metadata.
+        theirs.create_branch(&mut theirs_ctx, "c", "c.txt", "c\n");
+        theirs_ctx.push_metadata("origin").unwrap();
+        let shared = read_metadata_ref(&Repository::open(remote.path()).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(shared.get("b").unwrap().parent.as_deref(), Some("a"));
+        assert_eq!(shared.get("c").unwrap().parent.as_deref(), Some("main"));
+
+        ours_ctx.pull_metadata("origin").unwrap();
+        assert_eq!(ours_ctx.tree.get("b").unwrap().parent.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn push_propagates_explicit_deletions() {
+        let ours = TestRepo::new();
+        let remote = ours.add_remote();
+        let mut ctx = ours.ctx();
+        ours.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        ctx.push_metadata("origin").unwrap();
+
+        ctx.tree.delete("a").unwrap();
+        ctx.push_metadata("origin").unwrap();
+        let shared = read_metadata_ref(&Repository::open(remote.path()).unwrap())
+            .unwrap()
+            .unwrap();
+        assert!(shared.get("a").is_none());
+    }
+}
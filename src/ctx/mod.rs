@@ -1,74 +1,203 @@
 //! The in-memory context of the `st` application.
 
 use crate::{
-    config::StConfig,
-    constants::{GIT_DIR, ST_CTX_FILE_NAME},
+    config::{MetadataBackend, RepoConfig, StConfig},
+    constants::{ST_CTX_FILE_NAME, ST_JOURNAL_FILE_NAME, ST_LOCK_FILE_NAME, ST_RESTACK_FILE_NAME},
     errors::{StError, StResult},
+    git::RepositoryExt,
     tree::StackTree,
 };
 use git2::{BranchType, Repository};
-use std::path::PathBuf;
+use nu_ansi_term::Color;
+use std::{
+    collections::HashSet,
+    fs::{File, TryLockError},
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 mod actions;
+mod doctor;
+pub use doctor::StackProblem;
 mod fmt;
+pub(crate) use fmt::format_time_ago;
+mod journal;
+use journal::OperationSnapshot;
+pub use journal::{Journal, JournalOutcome};
+mod metadata;
+pub use metadata::MetadataSync;
+mod restack;
+pub use restack::{RestackPlan, RestackSummary};
+mod snapshot;
 mod stack_management;
+mod store;
 
 /// Returns the path to the persistent application context for the given [Repository].
 ///
+/// The context lives in the common directory of the repository, so that it is shared by all
+/// worktrees and is available in bare repositories.
+///
 /// ## Takes
 /// - `repository` - The repository to get the context path for.
 ///
 /// ## Returns
-/// - `Some(PathBuf)` - The path to the serialized context.
-/// - `None` - If the repository does not have a workdir.
-pub fn ctx_path(repository: &Repository) -> Option<PathBuf> {
-    repository
-        .workdir()
-        .map(|p| p.join(GIT_DIR).join(ST_CTX_FILE_NAME))
+/// - `PathBuf` - The path to the serialized context.
+pub fn ctx_path(repository: &Repository) -> PathBuf {
+    repository.common_dir().join(ST_CTX_FILE_NAME)
+}
+
+/// Returns the path to the pending restack plan for the given [Repository].
+///
+/// Restacks are resumed in the worktree they were started in, so the plan lives in the
+/// worktree's own git directory.
+///
+/// ## Takes
+/// - `repository` - The repository to get the restack plan path for.
+///
+/// ## Returns
+/// - `PathBuf` - The path to the serialized restack plan.
+pub fn restack_plan_path(repository: &Repository) -> PathBuf {
+    repository.path().join(ST_RESTACK_FILE_NAME)
+}
+
+/// Returns the path to the operation journal for the given [Repository], which is shared by all
+/// worktrees.
+///
+/// ## Takes
+/// - `repository` - The repository to get the journal path for.
+///
+/// ## Returns
+/// - `PathBuf` - The path to the serialized journal.
+pub fn journal_path(repository: &Repository) -> PathBuf {
+    repository.common_dir().join(ST_JOURNAL_FILE_NAME)
+}
+
+/// Acquires an advisory lock on the store of the given [Repository], which is held until the
+/// returned [File] is dropped.
+///
+/// ## Returns
+/// - `Result<File>` - The locked file, or [StError::StoreLocked] if another `st` process holds
+///   the lock.
+fn lock_store(repository: &Repository) -> StResult<File> {
+    let lock = File::options()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(repository.common_dir().join(ST_LOCK_FILE_NAME))?;
+    match lock.try_lock() {
+        Ok(()) => Ok(lock),
+        Err(TryLockError::WouldBlock) => Err(StError::StoreLocked),
+        Err(TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+/// Writes `contents` to `path` atomically, by writing them to a temporary file alongside it and
+/// renaming it into place. Readers observe either the old or the new contents, never a mix.
+pub(crate) fn write_atomic(path: &Path, contents: &str) -> StResult<()> {
+    let tmp_path = path.with_extension("tmp");
+    let mut tmp_file = File::create(&tmp_path)?;
+    tmp_file.write_all(contents.as_bytes())?;
+    tmp_file.sync_all()?;
+    std::fs::rename(tmp_path, path)?;
+    Ok(())
 }
 
 /// The in-memory context of the `st` application.
 pub struct StContext<'a> {
     /// The global configuration for `st`.
     pub cfg: StConfig,
+    /// The per-repository configuration for `st`.
+    pub repo_cfg: RepoConfig,
     /// The remote name associated with the current context.
     pub remote_name: Option<String>,
     /// The repository associated with the store.
     pub repository: &'a Repository,
     /// The tree of branches tracked by `st`.
     pub tree: StackTree,
+    /// The state of the tree and local branches when the context was loaded, which is recorded
+    /// in the [Journal] along with the state on save. [None] if the operation is not journaled.
+    snapshot: Option<OperationSnapshot>,
+    /// The branches that were tracked once the context was loaded. Branches that are missing
+    /// from the [StackTree] without having been among them were pruned rather than deleted, and
+    /// are kept in `refs/st/metadata` (see [StContext::write_metadata_ref]).
+    tracked_on_load: HashSet<String>,
+    /// The advisory lock on the store, held for as long as the context is alive.
+    _lock: File,
 }
 
 impl<'a> StContext<'a> {
-    /// Creates a fresh [StContext] with the given [Repository] and trunk branch name.
-    pub fn fresh(cfg: StConfig, repository: &'a Repository, trunk: String) -> Self {
-        Self {
-            cfg,
-            repository,
-            remote_name: None,
-            tree: StackTree::new(trunk),
-        }
-    }
-
-    /// Loads the [StackTree] for the given [Repository], and assembles a [StContext].
-    pub fn try_load(cfg: StConfig, repository: &'a Repository) -> StResult<Option<Self>> {
-        let store_path = ctx_path(repository).ok_or(StError::GitRepositoryRootNotFound)?;
+    /// Loads the [StackTree] for the given [Repository], and assembles a [StContext]. If the
+    /// repository is not set up with `st`, a fresh [StContext] is created instead, with the trunk
+    /// branch returned by `select_trunk`.
+    ///
+    /// The store is locked before it is read, and stays locked until the [StContext] is dropped.
+    /// `select_trunk` runs while the lock is held, so that two processes cannot set up the
+    /// repository at once.
+    pub fn load_or_initialize(
+        cfg: StConfig,
+        repo_cfg: RepoConfig,
+        repository: &'a Repository,
+        select_trunk: impl FnOnce() -> StResult<String>,
+    ) -> StResult<Self> {
+        let lock = lock_store(repository)?;
+        let store_path = ctx_path(repository);
 
-        // If the store doesn't exist, return None.
-        if !store_path.exists() {
-            return Ok(None);
-        }
-
-        let stack: StackTree = toml::from_str(&std::fs::read_to_string(store_path)?)?;
+        // Prefer the metadata ref if the stack is stored in git, falling back to the store file
+        // so that existing stacks carry over when switching backends.
+        let stack = match repo_cfg.metadata.backend {
+            MetadataBackend::Ref => metadata::read_metadata_ref(repository)?,
+            MetadataBackend::File => None,
+        };
+        let stack = match stack {
+            Some(stack) => stack,
+            // If the store doesn't exist, set up the repository.
+            None if !store_path.exists() => {
+                let trunk = select_trunk()?;
+                return Ok(Self::fresh_locked(cfg, repo_cfg, repository, trunk, lock));
+            }
+            None => store::read_store(&store_path)?,
+        };
         let mut store_with_repo = Self {
             cfg,
+            repo_cfg,
             repository,
             remote_name: None,
             tree: stack,
+            snapshot: None,
+            tracked_on_load: HashSet::new(),
+            _lock: lock,
         };
         store_with_repo.prune()?;
+        store_with_repo.refresh_parent_oid_caches()?;
+        store_with_repo.tracked_on_load = store_with_repo.tree.branches.keys().cloned().collect();
+        store_with_repo.snapshot = Some(OperationSnapshot::capture(
+            repository,
+            &store_with_repo.tree,
+        )?);
 
-        Ok(Some(store_with_repo))
+        Ok(store_with_repo)
+    }
+
+    /// Creates a fresh [StContext] with the given [Repository] and trunk branch name, holding
+    /// an already acquired lock on the store.
+    fn fresh_locked(
+        cfg: StConfig,
+        repo_cfg: RepoConfig,
+        repository: &'a Repository,
+        trunk: String,
+        lock: File,
+    ) -> Self {
+        let tree = StackTree::new(trunk);
+        Self {
+            cfg,
+            repo_cfg,
+            repository,
+            remote_name: None,
+            snapshot: OperationSnapshot::capture(repository, &tree).ok(),
+            tracked_on_load: tree.branches.keys().cloned().collect(),
+            tree,
+            _lock: lock,
+        }
     }
 
     /// Parses the GitHub owner and repository from the current repository's remote URL.
@@ -117,14 +246,64 @@ impl<'a> StContext<'a> {
     }
 
     /// Prunes branches in the context that no longer exist in the git repository.
+    ///
+    /// Branches that cannot be unlinked cleanly because the tree is inconsistent are removed
+    /// outright, leaving the remaining links for `st doctor` to repair.
+    ///
+    /// Pruning only affects the local view of the stack. Pruned branches are kept in
+    /// `refs/st/metadata`, so that branches which were never fetched are not deleted for
+    /// everyone sharing the metadata.
     fn prune(&mut self) -> StResult<()> {
-        let branches = self.tree.branches()?;
-        branches.iter().try_for_each(|b| {
-            if self.repository.find_branch(b, BranchType::Local).is_err() {
-                self.tree.delete(b)?;
+        let mut branches = self.tree.branches.keys().cloned().collect::<Vec<_>>();
+        branches.sort();
+        for branch in branches {
+            if branch == self.tree.trunk_name
+                || self
+                    .repository
+                    .find_branch(&branch, BranchType::Local)
+                    .is_ok()
+            {
+                continue;
+            }
+
+            if self.tree.delete(&branch).is_err() {
+                self.tree.branches.remove(&branch);
+            }
+            if self.repo_cfg.metadata.backend == MetadataBackend::File {
+                println!(
+                    "Stopped tracking branch `{}`, which no longer exists locally.",
+                    Color::Green.paint(&branch)
+                );
             }
-            Ok::<_, StError>(())
-        })
+        }
+        Ok(())
+    }
+
+    /// Persists the [StackTree] to the configured backend, and records the operation in the
+    /// [Journal].
+    ///
+    /// The store is written atomically, so a failure part way through leaves the previous store
+    /// intact. The context is saved once a command succeeds; commands that leave changes behind
+    /// when they fail part way through save before returning the error. Saving more than once
+    /// records each part of the operation as its own [Journal] entry.
+    pub fn save(&mut self) -> StResult<()> {
+        match self.repo_cfg.metadata.backend {
+            MetadataBackend::File => {
+                store::write_store(&ctx_path(self.repository), &self.tree)?;
+            }
+            MetadataBackend::Ref => {
+                self.write_metadata_ref()?;
+            }
+        }
+
+        // Record the operation in the journal, so that it can be undone, and start the next
+        // entry from the saved state.
+        self.record_operation()?;
+        self.tracked_on_load = self.tree.branches.keys().cloned().collect();
+        if self.snapshot.is_some() {
+            self.snapshot = Some(OperationSnapshot::capture(self.repository, &self.tree)?);
+        }
+        Ok(())
     }
 
     /// Overwrites the remote name associated with the current context.
@@ -133,11 +312,61 @@ impl<'a> StContext<'a> {
     }
 }
 
-impl Drop for StContext<'_> {
-    fn drop(&mut self) {
-        // Persist the store on drop.
-        let store_path = ctx_path(self.repository).expect("Failed to get context path.");
-        let store = toml::to_string_pretty(&self.tree).expect("Failed to serialize context.");
-        std::fs::write(store_path, store).expect("Failed to persist context to disk.");
+#[cfg(test)]
+mod test {
+    use super::{ctx_path, journal_path, restack_plan_path, StContext};
+    use crate::{
+        config::{RepoConfig, StConfig},
+        errors::StError,
+        test_utils::TestRepo,
+    };
+    use git2::Repository;
+
+    #[test]
+    fn store_is_locked_before_setup() {
+        let test_repo = TestRepo::new();
+        let _ctx = test_repo.ctx();
+
+        let second = StContext::load_or_initialize(
+            StConfig::default(),
+            RepoConfig::default(),
+            &test_repo.repo,
+            || panic!("The trunk branch must not be selected without holding the lock."),
+        );
+        assert!(matches!(second, Err(StError::StoreLocked)));
+    }
+
+    #[test]
+    fn worktrees_share_the_store() {
+        let test_repo = TestRepo::new();
+        test_repo.git(&["branch", "a"]);
+        let worktree_dir = tempfile::tempdir().unwrap();
+        let worktree_path = worktree_dir.path().join("a");
+        test_repo.git(&[
+            "worktree",
+            "add",
+            "-q",
+            worktree_path.to_str().unwrap(),
+            "a",
+        ]);
+        let worktree = Repository::open(&worktree_path).unwrap();
+
+        let common_dir = test_repo.repo.path().canonicalize().unwrap();
+        let parent_dir = |path: std::path::PathBuf| path.parent().unwrap().canonicalize().unwrap();
+        assert_eq!(parent_dir(ctx_path(&worktree)), common_dir);
+        assert_eq!(parent_dir(journal_path(&worktree)), common_dir);
+        assert_ne!(parent_dir(restack_plan_path(&worktree)), common_dir);
+
+        // The lock is shared as well.
+        let _ctx = test_repo.ctx();
+        assert!(matches!(
+            StContext::load_or_initialize(
+                StConfig::default(),
+                RepoConfig::default(),
+                &worktree,
+                || Ok("main".to_string())
+            ),
+            Err(StError::StoreLocked)
+        ));
     }
 }
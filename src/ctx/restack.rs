@@ -0,0 +1,1095 @@
+//! Restacking functionality for [StContext].
+
+use super::StContext;
+use crate::{
+    config::RestackStrategy,
+    constants::QUOTE_CHAR,
+    ctx::{
+        restack_plan_path,
+        store::{decode_versioned, encode_versioned},
+        write_atomic,
+    },
+    errors::{StError, StResult},
+    git::{ReplayOutcome, RepositoryExt},
+};
+use git2::{BranchType, Oid, Repository};
+use nu_ansi_term::Color;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Display,
+};
+
+impl StContext<'_> {
+    /// Restacks the branch onto the parent branch passed.
+    ///
+    /// Returns [RestackStatus::Restacked] if the branch was restacked, and
+    /// [RestackStatus::UpToDate] if it did not need to be.
+    pub fn restack_branch(
+        &mut self,
+        branch_name: &str,
+        parent_name: &str,
+    ) -> StResult<RestackStatus> {
+        self.restack_branch_from(branch_name, parent_name, None)
+    }
+
+    /// Restacks the branch onto the parent branch passed, moving only the commits that are not
+    /// reachable from `upstream`. If `upstream` is [None], the branch's fork point is used as the
+    /// upstream (see [StContext::fork_point]).
+    ///
+    /// Returns [RestackStatus::Restacked] if the branch was restacked,
+    /// [RestackStatus::UpToDate] if it did not need to be, and [RestackStatus::Skipped] if it is
+    /// checked out in another worktree.
+    pub fn restack_branch_from(
+        &mut self,
+        branch_name: &str,
+        parent_name: &str,
+        upstream: Option<Oid>,
+    ) -> StResult<RestackStatus> {
+        let parent_oid = self
+            .repository
+            .find_branch(parent_name, BranchType::Local)?
+            .get()
+            .target()
+            .ok_or(StError::MissingParentOidCache)?;
+
+        // Skip branches that do not need to be restacked. A branch with an explicit upstream is
+        // being moved, and needs to be rebased even if the parent is already one of its
+        // ancestors, unless its commits already sit directly on top of the parent.
+        let is_up_to_date = match upstream {
+            Some(upstream) => upstream == parent_oid,
+            None => !self.needs_restack(branch_name)?,
+        };
+        if is_up_to_date {
+            println!(
+                "Branch `{}` does not need to be restacked onto `{}`.",
+                Color::Green.paint(branch_name),
+                Color::Yellow.paint(parent_name)
+            );
+            return Ok(RestackStatus::UpToDate);
+        }
+
+        // Leave branches that are checked out in other worktrees alone, so that their working
+        // trees are not rewritten from underneath them.
+        if let Some(path) = self.repository.branch_worktree(branch_name)? {
+            println!(
+                "Branch `{}` is checked out in the worktree at `{}`. Skipping.",
+                Color::Green.paint(branch_name),
+                path.display()
+            );
+            return Ok(RestackStatus::Skipped);
+        }
+
+        let head_oid = self
+            .repository
+            .find_branch(branch_name, BranchType::Local)?
+            .get()
+            .target()
+            .ok_or(StError::BranchUnavailable)?;
+
+        // If the branch is checked out, its working tree is rewritten along with the branch.
+        let is_checked_out =
+            self.repository.current_branch_name().ok().as_deref() == Some(branch_name);
+        if is_checked_out && !self.repository.is_working_tree_clean()? {
+            return Err(StError::WorkingTreeDirty);
+        }
+
+        // Branches with an explicit upstream are always rebased, as the caller intends to rewrite
+        // their history.
+        match (upstream, self.repo_cfg.restack.strategy) {
+            (None, RestackStrategy::Merge) => self.merge_parent_into(
+                branch_name,
+                parent_name,
+                head_oid,
+                parent_oid,
+                is_checked_out,
+            )?,
+            (upstream, _) => {
+                let upstream = match upstream {
+                    Some(upstream) => upstream,
+                    None => self.fork_point(branch_name, head_oid, parent_oid)?,
+                };
+                self.rebase_onto_parent(
+                    branch_name,
+                    parent_name,
+                    head_oid,
+                    parent_oid,
+                    upstream,
+                    is_checked_out,
+                )?
+            }
+        }
+
+        // Update the parent oid cache.
+        self.tree
+            .get_mut(branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(branch_name.to_string()))?
+            .parent_oid_cache = Some(parent_oid.to_string());
+
+        println!(
+            "Restacked branch `{}` onto `{}`.",
+            Color::Green.paint(branch_name),
+            Color::Yellow.paint(parent_name)
+        );
+        Ok(RestackStatus::Restacked)
+    }
+
+    /// Replays the branch's commits after `upstream` onto its parent in memory, falling back to an
+    /// on-disk rebase if a conflict needs to be resolved manually.
+    fn rebase_onto_parent(
+        &self,
+        branch_name: &str,
+        parent_name: &str,
+        head_oid: Oid,
+        parent_oid: Oid,
+        upstream: Oid,
+        is_checked_out: bool,
+    ) -> StResult<()> {
+        match self
+            .repository
+            .replay_commits(head_oid, upstream, parent_oid)?
+        {
+            ReplayOutcome::Complete(new_head) => {
+                self.repository.reference(
+                    format!("refs/heads/{}", branch_name).as_str(),
+                    new_head,
+                    true,
+                    format!("st: restack onto {}", parent_name).as_str(),
+                )?;
+                if is_checked_out {
+                    self.repository.force_checkout_branch(branch_name)?;
+                }
+            }
+            ReplayOutcome::Conflict { .. } => {
+                let upstream = upstream.to_string();
+                if let Err(e) = self.repository.rebase_branch_onto(
+                    branch_name,
+                    parent_name,
+                    Some(upstream.as_str()),
+                ) {
+                    eprintln!(
+                        "Failed to rebase branch `{}` onto `{}`",
+                        Color::Green.paint(branch_name),
+                        Color::Yellow.paint(parent_name),
+                    );
+                    return Err(e.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges the parent into the branch in memory, falling back to an on-disk merge if a
+    /// conflict needs to be resolved manually.
+    fn merge_parent_into(
+        &self,
+        branch_name: &str,
+        parent_name: &str,
+        head_oid: Oid,
+        parent_oid: Oid,
+        is_checked_out: bool,
+    ) -> StResult<()> {
+        let message = format!("Merge branch '{}' into {}", parent_name, branch_name);
+        match self
+            .repository
+            .merge_commit(head_oid, parent_oid, &message)?
+        {
+            ReplayOutcome::Complete(merge_commit) => {
+                self.repository.reference(
+                    format!("refs/heads/{}", branch_name).as_str(),
+                    merge_commit,
+                    true,
+                    format!("st: merge {}", parent_name).as_str(),
+                )?;
+                if is_checked_out {
+                    self.repository.force_checkout_branch(branch_name)?;
+                }
+            }
+            ReplayOutcome::Conflict { .. } => {
+                if let Err(e) = self.repository.merge_branch_into(branch_name, parent_name) {
+                    eprintln!(
+                        "Failed to merge branch `{}` into `{}`",
+                        Color::Yellow.paint(parent_name),
+                        Color::Green.paint(branch_name),
+                    );
+                    return Err(e.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether or not a rebase or merge started by a restack stopped due to conflicts.
+    fn is_restack_interrupted(&self) -> bool {
+        self.repository.is_rebase_in_progress() || self.repository.is_merge_in_progress()
+    }
+
+    /// Aborts the rebase or merge of a restack that stopped due to conflicts, if there is one.
+    pub fn abort_interrupted_restack(&self) -> StResult<()> {
+        if self.repository.is_rebase_in_progress() {
+            self.repository.abort_rebase()?;
+        } else if self.repository.is_merge_in_progress() {
+            self.repository.abort_merge()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the commit that the branch forked from its parent at, which is the exclusive lower
+    /// bound of the branch's own commits.
+    ///
+    /// This is the branch's parent [git2::Oid] cache, so that commits of the parent that were
+    /// since amended or squashed are not replayed. If the cache is missing or is not an ancestor
+    /// of the branch, the merge-base of the branch and its parent is used instead.
+    pub fn fork_point(&self, branch_name: &str, head_oid: Oid, parent_oid: Oid) -> StResult<Oid> {
+        let parent_oid_cache = self
+            .tree
+            .get(branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(branch_name.to_string()))?
+            .parent_oid_cache
+            .as_deref()
+            .and_then(|oid| Oid::from_str(oid).ok());
+
+        if let Some(cache) = parent_oid_cache {
+            if cache == head_oid || self.repository.graph_descendant_of(head_oid, cache)? {
+                return Ok(cache);
+            }
+        }
+        Ok(self.repository.merge_base(head_oid, parent_oid)?)
+    }
+
+    /// Restacks each of the passed branches onto its parent in the [StackTree], in order. The
+    /// passed branches must be ordered such that parents are listed before their children.
+    ///
+    /// The outcome for each branch is recorded in the passed [RestackSummary]. If a branch
+    /// conflicts with its parent, the remainder of the restack is persisted as a [RestackPlan] so
+    /// that it can be resumed with `st continue` or rolled back with `st abort`.
+    ///
+    /// [StackTree]: crate::tree::StackTree
+    pub fn restack_branches(
+        &mut self,
+        branches: &[String],
+        summary: &mut RestackSummary,
+    ) -> StResult<()> {
+        self.check_no_restack_in_progress()?;
+
+        let plan = RestackPlan::new(self, branches)?;
+        self.run_restack_plan(plan, summary)
+    }
+
+    /// Predicts the outcome of restacking each of the passed branches onto its parent, in order,
+    /// without changing any refs or the working tree. The passed branches must be ordered such
+    /// that parents are listed before their children.
+    pub fn check_restack(&self, branches: &[String]) -> StResult<RestackCheck> {
+        let mut check = RestackCheck::default();
+
+        // The `HEAD` commits that branches would have after the restack, and the branches that
+        // would conflict or sit on top of a conflict.
+        let mut simulated_heads = HashMap::<String, Oid>::new();
+        let mut blocked = HashSet::<String>::new();
+
+        for branch_name in branches {
+            let tracked_branch = self
+                .tree
+                .get(branch_name)
+                .ok_or_else(|| StError::BranchNotTracked(branch_name.to_string()))?;
+
+            // The trunk branch is never restacked.
+            let Some(parent_name) = tracked_branch.parent.clone() else {
+                continue;
+            };
+
+            if blocked.contains(&parent_name) {
+                blocked.insert(branch_name.clone());
+                check.push(branch_name, &parent_name, RestackPrediction::Blocked);
+                continue;
+            }
+
+            let parent_oid = match simulated_heads.get(&parent_name) {
+                Some(oid) => *oid,
+                None if !self.needs_restack(branch_name)? => {
+                    check.push(branch_name, &parent_name, RestackPrediction::UpToDate);
+                    continue;
+                }
+                None => self
+                    .repository
+                    .find_branch(&parent_name, BranchType::Local)?
+                    .get()
+                    .target()
+                    .ok_or(StError::BranchUnavailable)?,
+            };
+            let head_oid = self
+                .repository
+                .find_branch(branch_name, BranchType::Local)?
+                .get()
+                .target()
+                .ok_or(StError::BranchUnavailable)?;
+
+            let outcome = match self.repo_cfg.restack.strategy {
+                RestackStrategy::Merge => self.repository.merge_commit(
+                    head_oid,
+                    parent_oid,
+                    &format!("Merge branch '{}' into {}", parent_name, branch_name),
+                )?,
+                RestackStrategy::Rebase => {
+                    let upstream = self.fork_point(branch_name, head_oid, parent_oid)?;
+                    self.repository
+                        .replay_commits(head_oid, upstream, parent_oid)?
+                }
+            };
+
+            match outcome {
+                ReplayOutcome::Complete(new_head) => {
+                    simulated_heads.insert(branch_name.clone(), new_head);
+                    check.push(branch_name, &parent_name, RestackPrediction::Clean);
+                }
+                ReplayOutcome::Conflict { paths, .. } => {
+                    blocked.insert(branch_name.clone());
+                    check.push(
+                        branch_name,
+                        &parent_name,
+                        RestackPrediction::Conflict(paths),
+                    );
+                }
+            }
+        }
+
+        Ok(check)
+    }
+
+    /// Returns an error if a restack stopped due to conflicts and has not yet been continued or
+    /// aborted.
+    pub fn check_no_restack_in_progress(&self) -> StResult<()> {
+        if RestackPlan::try_load(self.repository)?.is_some() {
+            return Err(StError::RestackInProgress);
+        }
+        Ok(())
+    }
+
+    /// Executes the remaining steps of a [RestackPlan].
+    ///
+    /// Once all steps have been executed, the plan is cleared and the originally checked out
+    /// branch is checked out again.
+    pub fn run_restack_plan(
+        &mut self,
+        mut plan: RestackPlan,
+        summary: &mut RestackSummary,
+    ) -> StResult<()> {
+        while let Some(step) = plan.steps.front().cloned() {
+            let upstream = step.upstream.as_deref().map(Oid::from_str).transpose()?;
+            match self.restack_branch_from(&step.branch, &step.parent, upstream) {
+                Ok(status) => {
+                    summary.push(&step.branch, &step.parent, status);
+                    plan.steps.pop_front();
+                }
+                Err(e) => {
+                    summary.push(&step.branch, &step.parent, RestackStatus::Failed);
+
+                    // The branches restacked so far have moved, so the context is saved even
+                    // though the restack failed. If the rebase or merge stopped due to
+                    // conflicts, persist the plan so that the restack can be resumed once the
+                    // conflicts are resolved.
+                    if self.is_restack_interrupted() {
+                        eprintln!("{}", e);
+                        plan.save(self.repository)?;
+                        self.save()?;
+                        return Err(StError::RestackConflict(step.branch));
+                    }
+
+                    RestackPlan::clear(self.repository)?;
+                    self.save()?;
+                    return Err(e);
+                }
+            }
+        }
+
+        // Clear the plan and return to the originally checked out branch.
+        RestackPlan::clear(self.repository)?;
+        if self.repository.current_branch_name()? != plan.checked_out {
+            self.repository.checkout_branch(&plan.checked_out)?;
+        }
+        Ok(())
+    }
+
+    /// Resumes a restack that was stopped due to conflicts.
+    ///
+    /// Finishes the in-progress rebase or merge of the conflicting branch, updates its parent
+    /// [git2::Oid] cache, and restacks the remaining branches in the plan.
+    pub fn continue_restack(&mut self, summary: &mut RestackSummary) -> StResult<()> {
+        let mut plan =
+            RestackPlan::try_load(self.repository)?.ok_or(StError::NoRestackInProgress)?;
+
+        // Finish the in-progress rebase or merge of the conflicting branch.
+        if let Some(step) = plan.steps.pop_front() {
+            if self.repository.is_rebase_in_progress() {
+                self.repository.continue_rebase()?;
+            } else if self.repository.is_merge_in_progress() {
+                self.repository.continue_merge()?;
+            }
+
+            let parent_oid = self
+                .repository
+                .find_branch(&step.parent, BranchType::Local)?
+                .get()
+                .target()
+                .ok_or(StError::MissingParentOidCache)?;
+            self.tree
+                .get_mut(&step.branch)
+                .ok_or_else(|| StError::BranchNotTracked(step.branch.to_string()))?
+                .parent_oid_cache = Some(parent_oid.to_string());
+
+            println!(
+                "Restacked branch `{}` onto `{}`.",
+                Color::Green.paint(&step.branch),
+                Color::Yellow.paint(&step.parent)
+            );
+            summary.push(&step.branch, &step.parent, RestackStatus::Restacked);
+        }
+
+        self.run_restack_plan(plan, summary)
+    }
+
+    /// Aborts a restack that was stopped due to conflicts, returning every branch in the plan
+    /// to the commit it pointed to before the restack started.
+    pub fn abort_restack(&mut self) -> StResult<()> {
+        let plan = RestackPlan::try_load(self.repository)?.ok_or(StError::NoRestackInProgress)?;
+
+        // Abort the in-progress rebase or merge, if there is one.
+        self.abort_interrupted_restack()?;
+
+        // Returning to the originally checked out branch resets the working tree, so refuse to
+        // discard changes made since the restack stopped. The plan is kept to retry the abort.
+        if !self.repository.is_working_tree_clean()? {
+            return Err(StError::WorkingTreeDirty);
+        }
+
+        // Detach every branch in the plan onto trunk, so that restoring the original parents can
+        // never create a cycle when branches were reordered.
+        let trunk_name = self.tree.trunk_name.clone();
+        for (branch_name, snapshot) in plan.snapshots.iter() {
+            if snapshot.parent.is_some() {
+                self.tree.reparent(branch_name, &trunk_name)?;
+            }
+        }
+
+        // Restore the branch heads and parent oid caches.
+        for (branch_name, snapshot) in plan.snapshots.iter() {
+            let head = Oid::from_str(&snapshot.head)?;
+            self.repository.reference(
+                format!("refs/heads/{}", branch_name).as_str(),
+                head,
+                true,
+                "st: abort restack",
+            )?;
+
+            if let Some(parent) = snapshot.parent.as_ref() {
+                self.tree.reparent(branch_name, parent)?;
+            }
+            if let Some(tracked_branch) = self.tree.get_mut(branch_name) {
+                tracked_branch.parent_oid_cache = snapshot.parent_oid_cache.clone();
+            }
+        }
+
+        // Return to the originally checked out branch.
+        self.repository.force_checkout_branch(&plan.checked_out)?;
+        RestackPlan::clear(self.repository)?;
+        Ok(())
+    }
+
+    /// Performs a restack of the active stack.
+    pub fn restack(&mut self) -> StResult<()> {
+        let stack = self.discover_stack()?;
+        self.restack_branches(&stack, &mut RestackSummary::default())
+    }
+
+    /// Restacks the given branch and every one of its descendants onto their parents, in
+    /// topological order.
+    pub fn restack_upstack(
+        &mut self,
+        branch_name: &str,
+        summary: &mut RestackSummary,
+    ) -> StResult<()> {
+        let branches = self.tree.subtree(branch_name)?;
+        self.restack_branches(&branches, summary)
+    }
+}
+
+/// A pending restack, persisted to disk when a branch conflicts with its parent.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RestackPlan {
+    /// The branch that was checked out when the restack started.
+    pub checked_out: String,
+    /// The remaining steps of the restack. If the restack stopped due to conflicts, the first
+    /// step is the branch being rebased.
+    pub steps: VecDeque<RestackStep>,
+    /// The state of each branch in the plan before the restack started.
+    pub snapshots: HashMap<String, BranchSnapshot>,
+}
+
+impl RestackPlan {
+    /// Creates a new [RestackPlan] for the passed branches, snapshotting their current state.
+    pub fn new(ctx: &StContext<'_>, branches: &[String]) -> StResult<Self> {
+        let mut steps = VecDeque::with_capacity(branches.len());
+        let mut snapshots = HashMap::with_capacity(branches.len());
+
+        for branch in branches {
+            let tracked_branch = ctx
+                .tree
+                .get(branch)
+                .ok_or_else(|| StError::BranchNotTracked(branch.to_string()))?;
+
+            // The trunk branch is never restacked.
+            let Some(parent) = tracked_branch.parent.clone() else {
+                continue;
+            };
+
+            let head = ctx
+                .repository
+                .find_branch(branch, BranchType::Local)?
+                .get()
+                .target()
+                .ok_or(StError::BranchUnavailable)?;
+
+            steps.push_back(RestackStep {
+                branch: branch.clone(),
+                parent: parent.clone(),
+                upstream: None,
+            });
+            snapshots.insert(
+                branch.clone(),
+                BranchSnapshot {
+                    head: head.to_string(),
+                    parent: Some(parent.clone()),
+                    parent_oid_cache: tracked_branch.parent_oid_cache.clone(),
+                },
+            );
+        }
+
+        Ok(Self {
+            checked_out: ctx.repository.current_branch_name()?,
+            steps,
+            snapshots,
+        })
+    }
+
+    /// Retargets the step for the passed branch onto a new parent.
+    pub fn retarget(&mut self, branch_name: &str, parent_name: &str) {
+        if let Some(step) = self.steps.iter_mut().find(|s| s.branch == branch_name) {
+            step.parent = parent_name.to_string();
+        }
+    }
+
+    /// Uses each branch's parent [git2::Oid] cache as its upstream, so that only the branch's own
+    /// commits are moved when it is restacked.
+    pub fn use_cached_upstreams(&mut self) {
+        for step in self.steps.iter_mut() {
+            step.upstream = self
+                .snapshots
+                .get(&step.branch)
+                .and_then(|s| s.parent_oid_cache.clone());
+        }
+    }
+
+    /// Loads the pending [RestackPlan] for the given [Repository], if one exists.
+    pub fn try_load(repository: &Repository) -> StResult<Option<Self>> {
+        let plan_path = restack_plan_path(repository);
+        if !plan_path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(
+            decode_versioned(&std::fs::read_to_string(plan_path)?)?.0,
+        ))
+    }
+
+    /// Persists the [RestackPlan] for the given [Repository].
+    pub fn save(&self, repository: &Repository) -> StResult<()> {
+        write_atomic(&restack_plan_path(repository), &encode_versioned(self)?)
+    }
+
+    /// Removes the pending [RestackPlan] for the given [Repository], if one exists.
+    pub fn clear(repository: &Repository) -> StResult<()> {
+        let plan_path = restack_plan_path(repository);
+        if plan_path.exists() {
+            std::fs::remove_file(plan_path)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single step of a [RestackPlan].
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RestackStep {
+    /// The branch to restack.
+    pub branch: String,
+    /// The parent branch to restack onto.
+    pub parent: String,
+    /// The upstream commit of the branch, in string form. If [None], the parent branch is used
+    /// as the upstream.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream: Option<String>,
+}
+
+/// The state of a branch before a restack started.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BranchSnapshot {
+    /// The [git2::Oid] of the branch's `HEAD` commit, in string form.
+    pub head: String,
+    /// The branch's parent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// The branch's parent [git2::Oid] cache, in string form.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_oid_cache: Option<String>,
+}
+
+/// The outcome of restacking a single branch.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RestackStatus {
+    /// The branch was already up to date with its parent.
+    UpToDate,
+    /// The branch was restacked onto its parent.
+    Restacked,
+    /// The branch failed to restack onto its parent.
+    Failed,
+    /// The branch is checked out in another worktree, and was not restacked.
+    Skipped,
+}
+
+/// A per-branch summary of a restack operation, which implements [Display].
+#[derive(Debug, Default)]
+pub struct RestackSummary {
+    /// The branch name, parent name, and [RestackStatus] for each branch visited.
+    entries: Vec<(String, String, RestackStatus)>,
+}
+
+impl RestackSummary {
+    /// Records the outcome of restacking `branch` onto `parent`.
+    pub fn push(&mut self, branch: &str, parent: &str, status: RestackStatus) {
+        self.entries
+            .push((branch.to_string(), parent.to_string(), status));
+    }
+
+    /// Returns `true` if no branches were visited.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Display for RestackSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (branch, parent, status) in self.entries.iter() {
+            let (icon, description) = match status {
+                RestackStatus::UpToDate => ("✅", "already up to date with"),
+                RestackStatus::Restacked => ("🧙", "restacked onto"),
+                RestackStatus::Failed => ("❌", "failed to restack onto"),
+                RestackStatus::Skipped => {
+                    ("🌲", "is checked out elsewhere, skipped restacking onto")
+                }
+            };
+            writeln!(
+                f,
+                "{} `{}` {} `{}`",
+                icon,
+                Color::Green.paint(branch),
+                description,
+                Color::Yellow.paint(parent)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The predicted outcome of restacking a single branch.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RestackPrediction {
+    /// The branch is already up to date with its parent.
+    UpToDate,
+    /// The branch would restack cleanly onto its parent.
+    Clean,
+    /// The branch would conflict with its parent in the contained paths.
+    Conflict(Vec<String>),
+    /// The branch sits on top of a branch that would conflict, and could not be checked.
+    Blocked,
+}
+
+/// A per-branch prediction of a restack operation, which implements [Display].
+#[derive(Debug, Default)]
+pub struct RestackCheck {
+    /// The branch name, parent name, and [RestackPrediction] for each branch visited.
+    entries: Vec<(String, String, RestackPrediction)>,
+}
+
+impl RestackCheck {
+    /// Records the predicted outcome of restacking `branch` onto `parent`.
+    pub fn push(&mut self, branch: &str, parent: &str, prediction: RestackPrediction) {
+        self.entries
+            .push((branch.to_string(), parent.to_string(), prediction));
+    }
+
+    /// Returns the branches that would conflict, or sit on top of a branch that would conflict.
+    pub fn conflicting(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().filter_map(|(branch, _, prediction)| {
+            matches!(
+                prediction,
+                RestackPrediction::Conflict(_) | RestackPrediction::Blocked
+            )
+            .then_some(branch.as_str())
+        })
+    }
+}
+
+impl Display for RestackCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (branch, parent, prediction) in self.entries.iter() {
+            let branch = Color::Green.paint(branch);
+            let parent = Color::Yellow.paint(parent);
+            match prediction {
+                RestackPrediction::UpToDate => {
+                    writeln!(f, "✅ `{}` already up to date with `{}`", branch, parent)?
+                }
+                RestackPrediction::Clean => {
+                    writeln!(f, "🧙 `{}` restacks cleanly onto `{}`", branch, parent)?
+                }
+                RestackPrediction::Conflict(paths) => {
+                    writeln!(f, "💥 `{}` conflicts with `{}`", branch, parent)?;
+                    for path in paths {
+                        writeln!(f, "   {} {}", QUOTE_CHAR, Color::Red.paint(path))?;
+                    }
+                }
+                RestackPrediction::Blocked => writeln!(
+                    f,
+                    "🚧 `{}` sits on top of a conflict, and was not checked against `{}`",
+                    branch, parent
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RestackPlan, RestackPrediction, RestackSummary};
+    use crate::{
+        ctx::{
+            ctx_path, restack_plan_path,
+            store::{decode_versioned, read_store, STORE_VERSION},
+            StContext,
+        },
+        errors::StError,
+        git::RepositoryExt,
+        test_utils::TestRepo,
+    };
+    use git2::Oid;
+
+    #[test]
+    fn explicit_upstream_rebases_onto_ancestor() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        let a_head = test_repo.create_branch(&mut ctx, "b", "b.txt", "b\n");
+        let a_head = test_repo
+            .repo
+            .find_commit(a_head)
+            .unwrap()
+            .parent_id(0)
+            .unwrap();
+
+        // Move `b` down onto `main`, which is already one of its ancestors.
+        let mut plan = RestackPlan::new(&ctx, &["b".to_string()]).unwrap();
+        ctx.tree.reparent("b", "main").unwrap();
+        plan.retarget("b", "main");
+        plan.use_cached_upstreams();
+        assert_eq!(
+            plan.steps[0].upstream.as_deref(),
+            Some(a_head.to_string().as_str())
+        );
+        ctx.run_restack_plan(plan, &mut RestackSummary::default())
+            .unwrap();
+
+        assert_eq!(test_repo.log("b"), ["b", "initial commit"]);
+        assert_eq!(test_repo.read("b", "a.txt"), None);
+        assert_eq!(
+            ctx.tree.get("b").unwrap().parent_oid_cache,
+            Some(test_repo.head("main").to_string())
+        );
+        assert!(!ctx.needs_restack("b").unwrap());
+    }
+
+    #[test]
+    fn explicit_upstream_on_parent_is_up_to_date() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        let a_head = test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+
+        let mut plan = RestackPlan::new(&ctx, &["a".to_string()]).unwrap();
+        plan.use_cached_upstreams();
+        ctx.run_restack_plan(plan, &mut RestackSummary::default())
+            .unwrap();
+
+        assert_eq!(test_repo.head("a"), a_head);
+    }
+
+    #[test]
+    fn conflict_saves_context() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        test_repo.checkout("main");
+        test_repo.commit("a.txt", "main\n", "main");
+
+        let plan = RestackPlan::new(&ctx, &["a".to_string()]).unwrap();
+        assert!(matches!(
+            ctx.run_restack_plan(plan, &mut RestackSummary::default()),
+            Err(StError::RestackConflict(branch)) if branch == "a"
+        ));
+        assert!(RestackPlan::try_load(&test_repo.repo).unwrap().is_some());
+        assert_eq!(read_store(&ctx_path(&test_repo.repo)).unwrap(), ctx.tree);
+    }
+
+    #[test]
+    fn restack_replays_in_memory() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        test_repo.create_branch(&mut ctx, "b", "b.txt", "b\n");
+        test_repo.checkout("a");
+        test_repo.commit("a.txt", "a 2\n", "a 2");
+
+        ctx.restack_branch("b", "a").unwrap();
+
+        assert_eq!(test_repo.log("b"), ["b", "a 2", "a", "initial commit"]);
+        assert_eq!(test_repo.read("b", "a.txt").unwrap(), "a 2\n");
+        assert_eq!(test_repo.repo.current_branch_name().unwrap(), "a");
+        assert!(!test_repo.repo.is_rebase_in_progress());
+        assert!(!ctx.needs_restack("b").unwrap());
+    }
+
+    /// Sets up branch `a` to conflict with `main`, and starts restacking it, which falls back to
+    /// `git rebase --onto` and stops on the conflict.
+    fn start_conflicting_restack(test_repo: &TestRepo, ctx: &mut StContext<'_>) -> Oid {
+        let a_head = test_repo.create_branch(ctx, "a", "a.txt", "a\n");
+        test_repo.checkout("main");
+        test_repo.commit("a.txt", "main\n", "main");
+
+        let plan = RestackPlan::new(ctx, &["a".to_string()]).unwrap();
+        assert!(ctx
+            .run_restack_plan(plan, &mut RestackSummary::default())
+            .is_err());
+        assert!(test_repo.repo.is_rebase_in_progress());
+        a_head
+    }
+
+    #[test]
+    fn continue_after_resolving_conflict() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        start_conflicting_restack(&test_repo, &mut ctx);
+
+        test_repo.write("a.txt", "resolved\n");
+        test_repo.git(&["add", "a.txt"]);
+        ctx.continue_restack(&mut RestackSummary::default())
+            .unwrap();
+
+        assert_eq!(test_repo.log("a"), ["a", "main", "initial commit"]);
+        assert_eq!(test_repo.read("a", "a.txt").unwrap(), "resolved\n");
+        assert!(RestackPlan::try_load(&test_repo.repo).unwrap().is_none());
+        assert!(!ctx.needs_restack("a").unwrap());
+    }
+
+    #[test]
+    fn abort_restores_branches() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        let a_head = start_conflicting_restack(&test_repo, &mut ctx);
+
+        ctx.abort_restack().unwrap();
+
+        assert_eq!(test_repo.head("a"), a_head);
+        assert!(!test_repo.repo.is_rebase_in_progress());
+        assert!(RestackPlan::try_load(&test_repo.repo).unwrap().is_none());
+    }
+
+    /// Returns the branch and parent names paired with the predictions in `check`.
+    fn predictions(check: &super::RestackCheck) -> Vec<(&str, &str, RestackPrediction)> {
+        check
+            .entries
+            .iter()
+            .map(|(branch, parent, prediction)| {
+                (branch.as_str(), parent.as_str(), prediction.clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn check_up_to_date() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        test_repo.create_branch(&mut ctx, "b", "b.txt", "b\n");
+
+        let check = ctx
+            .check_restack(&["a".to_string(), "b".to_string()])
+            .unwrap();
+
+        assert_eq!(
+            predictions(&check),
+            [
+                ("a", "main", RestackPrediction::UpToDate),
+                ("b", "a", RestackPrediction::UpToDate)
+            ]
+        );
+        assert_eq!(check.conflicting().count(), 0);
+    }
+
+    #[test]
+    fn check_clean() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        let b_head = test_repo.create_branch(&mut ctx, "b", "b.txt", "b\n");
+        test_repo.checkout("main");
+        test_repo.commit("main.txt", "main\n", "main");
+
+        let check = ctx
+            .check_restack(&["a".to_string(), "b".to_string()])
+            .unwrap();
+
+        assert_eq!(
+            predictions(&check),
+            [
+                ("a", "main", RestackPrediction::Clean),
+                ("b", "a", RestackPrediction::Clean)
+            ]
+        );
+        assert_eq!(check.conflicting().count(), 0);
+
+        // The preview leaves the branches alone.
+        assert_eq!(test_repo.head("b"), b_head);
+        assert!(ctx.needs_restack("a").unwrap());
+    }
+
+    #[test]
+    fn check_conflict_reports_paths() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        let a_head = test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        test_repo.checkout("main");
+        test_repo.commit("a.txt", "main\n", "main");
+
+        let check = ctx.check_restack(&["a".to_string()]).unwrap();
+
+        assert_eq!(
+            predictions(&check),
+            [(
+                "a",
+                "main",
+                RestackPrediction::Conflict(vec!["a.txt".to_string()])
+            )]
+        );
+        assert_eq!(check.conflicting().collect::<Vec<_>>(), ["a"]);
+        assert_eq!(test_repo.head("a"), a_head);
+        assert!(!test_repo.repo.is_rebase_in_progress());
+    }
+
+    #[test]
+    fn check_blocks_descendants_of_conflicts() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        test_repo.create_branch(&mut ctx, "b", "b.txt", "b\n");
+        test_repo.checkout("main");
+        test_repo.commit("a.txt", "main\n", "main");
+
+        let check = ctx
+            .check_restack(&["a".to_string(), "b".to_string()])
+            .unwrap();
+
+        assert_eq!(
+            predictions(&check)[1],
+            ("b", "a", RestackPrediction::Blocked)
+        );
+        assert_eq!(check.conflicting().collect::<Vec<_>>(), ["a", "b"]);
+    }
+
+    /// Builds a forked stack of `main -> a -> {b, c}` and `b -> d`, and then amends `a`.
+    fn amend_forked_stack(test_repo: &TestRepo, ctx: &mut StContext<'_>) {
+        test_repo.create_branch(ctx, "a", "a.txt", "a\n");
+        test_repo.create_branch(ctx, "b", "b.txt", "b\n");
+        test_repo.create_branch(ctx, "d", "d.txt", "d\n");
+        test_repo.checkout("a");
+        test_repo.create_branch(ctx, "c", "c.txt", "c\n");
+        test_repo.checkout("a");
+        test_repo.write("a.txt", "amended\n");
+        test_repo.git(&["commit", "-a", "--amend", "--no-edit"]);
+    }
+
+    /// Asserts that every branch of the forked stack sits on top of the amended `a`.
+    fn assert_forked_stack_restacked(test_repo: &TestRepo, ctx: &StContext<'_>) {
+        assert_eq!(test_repo.log("b"), ["b", "a", "initial commit"]);
+        assert_eq!(test_repo.log("c"), ["c", "a", "initial commit"]);
+        assert_eq!(test_repo.log("d"), ["d", "b", "a", "initial commit"]);
+        for branch in ["b", "c", "d"] {
+            assert_eq!(test_repo.read(branch, "a.txt").unwrap(), "amended\n");
+            assert!(!ctx.needs_restack(branch).unwrap());
+        }
+    }
+
+    #[test]
+    fn restack_upstack_follows_forks() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        amend_forked_stack(&test_repo, &mut ctx);
+
+        ctx.restack_upstack("a", &mut RestackSummary::default())
+            .unwrap();
+
+        assert_forked_stack_restacked(&test_repo, &ctx);
+    }
+
+    #[test]
+    fn restack_all_follows_forks() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        amend_forked_stack(&test_repo, &mut ctx);
+
+        let branches = ctx.tree.branches().unwrap();
+        ctx.restack_branches(&branches, &mut RestackSummary::default())
+            .unwrap();
+
+        assert_forked_stack_restacked(&test_repo, &ctx);
+    }
+
+    #[test]
+    fn abort_keeps_uncommitted_changes() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        start_conflicting_restack(&test_repo, &mut ctx);
+        test_repo.git(&["rebase", "--abort"]);
+        test_repo.write("README.md", "uncommitted\n");
+
+        assert!(matches!(
+            ctx.abort_restack(),
+            Err(StError::WorkingTreeDirty)
+        ));
+        assert_eq!(
+            std::fs::read_to_string(test_repo.dir.path().join("README.md")).unwrap(),
+            "uncommitted\n"
+        );
+        assert!(RestackPlan::try_load(&test_repo.repo).unwrap().is_some());
+    }
+
+    #[test]
+    fn plan_is_versioned() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        start_conflicting_restack(&test_repo, &mut ctx);
+
+        let contents = std::fs::read_to_string(restack_plan_path(&test_repo.repo)).unwrap();
+        let (plan, version): (RestackPlan, _) = decode_versioned(&contents).unwrap();
+        assert_eq!(version, STORE_VERSION);
+        assert_eq!(RestackPlan::try_load(&test_repo.repo).unwrap(), Some(plan));
+    }
+}
@@ -0,0 +1,235 @@
+//! Named snapshots of the [StackTree] for [StContext].
+
+use super::{
+    metadata::{commit_stack, read_stack},
+    StContext,
+};
+use crate::{
+    constants::ST_SNAPSHOT_REF_PREFIX,
+    errors::{StError, StResult},
+    git::RepositoryExt,
+    tree::StackTree,
+};
+use git2::{BranchType, Oid, Reference};
+use nu_ansi_term::Color;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+impl StContext<'_> {
+    /// Saves a named snapshot of the [StackTree] and the `HEAD` commits of all tracked branches.
+    ///
+    /// The snapshot is stored as a commit under `refs/st/snapshots/<name>`, whose parents are
+    /// the tracked branch heads, so that they are never garbage collected.
+    ///
+    /// ## Takes
+    /// - `name` - The name of the snapshot.
+    /// - `force` - Whether to overwrite an existing snapshot with the same name.
+    pub fn save_snapshot(&self, name: &str, force: bool) -> StResult<StackSnapshot> {
+        let ref_name = snapshot_ref_name(name)?;
+        if !force && self.repository.find_reference(&ref_name).is_ok() {
+            return Err(StError::SnapshotAlreadyExists(name.to_string()));
+        }
+
+        // Capture the heads of every tracked branch.
+        let mut heads = BTreeMap::new();
+        for branch_name in self.tree.branches()? {
+            let head = self
+                .repository
+                .find_branch(&branch_name, BranchType::Local)?
+                .get()
+                .target()
+                .ok_or(StError::BranchUnavailable)?;
+            heads.insert(branch_name, head.to_string());
+        }
+        let snapshot = StackSnapshot {
+            tree: self.tree.clone(),
+            heads,
+        };
+
+        // Write the snapshot into a commit that keeps the branch heads reachable.
+        let mut parent_oids = snapshot
+            .heads
+            .values()
+            .map(|oid| Oid::from_str(oid))
+            .collect::<Result<Vec<_>, _>>()?;
+        parent_oids.sort();
+        parent_oids.dedup();
+        let parents = parent_oids
+            .into_iter()
+            .map(|oid| self.repository.find_commit(oid))
+            .collect::<Result<Vec<_>, _>>()?;
+        let commit = commit_stack(
+            self.repository,
+            &snapshot,
+            &format!("st snapshot: {}", name),
+            &parents.iter().collect::<Vec<_>>(),
+        )?;
+        self.repository
+            .reference(&ref_name, commit, true, "st: save snapshot")?;
+
+        Ok(snapshot)
+    }
+
+    /// Restores a named snapshot, resetting every branch in the snapshot to its saved `HEAD`
+    /// commit, recreating branches that were deleted, and replacing the [StackTree].
+    ///
+    /// The trunk branch is only reset if `include_trunk` is set, as it usually moved on for good
+    /// reasons. Branches that are checked out in other worktrees are skipped, as resetting them
+    /// would leave those worktrees out of sync. Branches that were created after the snapshot was
+    /// saved are left in place, but are no longer tracked.
+    pub fn restore_snapshot(&mut self, name: &str, include_trunk: bool) -> StResult<StackSnapshot> {
+        self.check_no_restack_in_progress()?;
+        if !self.repository.is_working_tree_clean()? {
+            return Err(StError::WorkingTreeDirty);
+        }
+
+        let snapshot = self.load_snapshot(name)?;
+        let current_branch = self.repository.current_branch_name().ok();
+
+        for (branch_name, head) in snapshot.heads.iter() {
+            if *branch_name == snapshot.tree.trunk_name && !include_trunk {
+                continue;
+            }
+            if let Some(path) = self.repository.branch_worktree(branch_name)? {
+                println!(
+                    "Skipped branch `{}`, which is checked out in the worktree at `{}`.",
+                    Color::Green.paint(branch_name),
+                    path.display()
+                );
+                continue;
+            }
+
+            self.repository.reference(
+                format!("refs/heads/{}", branch_name).as_str(),
+                Oid::from_str(head)?,
+                true,
+                format!("st: restore snapshot {}", name).as_str(),
+            )?;
+        }
+        self.tree = snapshot.tree.clone();
+
+        // Refresh the working tree to match the restored branch, falling back to trunk if the
+        // checked out branch is not part of the snapshot.
+        let checked_out = current_branch
+            .filter(|b| snapshot.heads.contains_key(b))
+            .unwrap_or_else(|| self.tree.trunk_name.clone());
+        self.repository.force_checkout_branch(&checked_out)?;
+
+        Ok(snapshot)
+    }
+
+    /// Deletes a named snapshot.
+    pub fn delete_snapshot(&self, name: &str) -> StResult<()> {
+        let ref_name = snapshot_ref_name(name)?;
+        self.repository
+            .find_reference(&ref_name)
+            .map_err(|_| StError::SnapshotNotFound(name.to_string()))?
+            .delete()?;
+        Ok(())
+    }
+
+    /// Lists all saved snapshots, sorted by name, along with the time they were saved at in
+    /// seconds since the Unix epoch.
+    pub fn list_snapshots(&self) -> StResult<Vec<(String, u64, StackSnapshot)>> {
+        let mut snapshots = Vec::new();
+        for reference in self
+            .repository
+            .references_glob(&format!("{}*", ST_SNAPSHOT_REF_PREFIX))?
+        {
+            let reference = reference?;
+            let Some(name) = reference
+                .name()
+                .and_then(|n| n.strip_prefix(ST_SNAPSHOT_REF_PREFIX))
+                .map(String::from)
+            else {
+                continue;
+            };
+
+            let commit = reference.peel_to_commit()?;
+            let timestamp = commit.time().seconds().max(0) as u64;
+            snapshots.push((name, timestamp, read_stack(self.repository, &commit)?));
+        }
+        snapshots.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(snapshots)
+    }
+
+    /// Loads a named snapshot.
+    fn load_snapshot(&self, name: &str) -> StResult<StackSnapshot> {
+        let ref_name = snapshot_ref_name(name)?;
+        let commit = self
+            .repository
+            .find_reference(&ref_name)
+            .map_err(|_| StError::SnapshotNotFound(name.to_string()))?
+            .peel_to_commit()?;
+        read_stack(self.repository, &commit)
+    }
+}
+
+/// Returns the full reference name of a named snapshot, validating the name.
+fn snapshot_ref_name(name: &str) -> StResult<String> {
+    let ref_name = format!("{}{}", ST_SNAPSHOT_REF_PREFIX, name);
+    if name.is_empty() || !Reference::is_valid_name(&ref_name) {
+        return Err(StError::InvalidSnapshotName(name.to_string()));
+    }
+    Ok(ref_name)
+}
+
+/// A named snapshot of the [StackTree] and the `HEAD` commits of its branches.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct StackSnapshot {
+    /// The [StackTree] at the time of the snapshot.
+    pub tree: StackTree,
+    /// A map of branch names to the [Oid] of their `HEAD` commits, in string form.
+    pub heads: BTreeMap<String, String>,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_utils::TestRepo;
+
+    #[test]
+    fn restore_resets_branches_but_not_trunk() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        let main_head = test_repo.head("main");
+        let a_head = test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        ctx.save_snapshot("before", false).unwrap();
+
+        test_repo.commit("a.txt", "changed\n", "change a");
+        test_repo.checkout("main");
+        let new_main_head = test_repo.commit("main.txt", "main\n", "main");
+        ctx.tree.delete("a").unwrap();
+
+        ctx.restore_snapshot("before", false).unwrap();
+        assert_eq!(test_repo.head("a"), a_head);
+        assert_eq!(test_repo.head("main"), new_main_head);
+        assert!(ctx.tree.get("a").is_some());
+
+        ctx.restore_snapshot("before", true).unwrap();
+        assert_eq!(test_repo.head("main"), main_head);
+    }
+
+    #[test]
+    fn restore_skips_branches_in_other_worktrees() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        ctx.save_snapshot("before", false).unwrap();
+        let new_a_head = test_repo.commit("a.txt", "changed\n", "change a");
+        test_repo.checkout("main");
+
+        let worktree_dir = tempfile::tempdir().unwrap();
+        let worktree_path = worktree_dir.path().join("a");
+        test_repo.git(&[
+            "worktree",
+            "add",
+            "-q",
+            worktree_path.to_str().unwrap(),
+            "a",
+        ]);
+
+        ctx.restore_snapshot("before", false).unwrap();
+        assert_eq!(test_repo.head("a"), new_a_head);
+    }
+}
@@ -5,7 +5,8 @@ use crate::{
     errors::{StError, StResult},
     git::RepositoryExt,
 };
-use git2::BranchType;
+use git2::{BranchType, Oid, Sort};
+use nu_ansi_term::Color;
 use std::collections::VecDeque;
 
 impl StContext<'_> {
@@ -57,7 +58,165 @@ impl StContext<'_> {
         Ok(stack.into())
     }
 
+    /// Resolves the child of the given branch to move upstack to.
+    ///
+    /// If the branch has multiple children, the stack forks and the user is prompted to select
+    /// which child to move to.
+    ///
+    /// ## Takes
+    /// - `branch_name` - The name of the branch to resolve the child of.
+    ///
+    /// ## Returns
+    /// - `Some(child)` - The name of the child branch.
+    /// - `None` - The branch is the tip of its stack.
+    pub fn resolve_child(&self, branch_name: &str) -> StResult<Option<String>> {
+        let branch = self
+            .tree
+            .get(branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(branch_name.to_string()))?;
+
+        let mut children = branch.children.iter().cloned().collect::<Vec<_>>();
+        match children.len() {
+            0 => Ok(None),
+            1 => Ok(children.pop()),
+            _ => {
+                children.sort();
+                let prompt = format!(
+                    "Branch `{}` has multiple children. Select the branch to move to",
+                    Color::Blue.paint(branch_name)
+                );
+                Ok(Some(inquire::Select::new(&prompt, children).prompt()?))
+            }
+        }
+    }
+
+    /// Resolves the branch `steps` branches upstack from the given branch, prompting the user
+    /// at each fork in the stack.
+    ///
+    /// Resolution stops early at the tip of the stack.
+    pub fn resolve_upstack(&self, branch_name: &str, steps: usize) -> StResult<String> {
+        let mut current = branch_name.to_string();
+        for _ in 0..steps {
+            match self.resolve_child(&current)? {
+                Some(child) => current = child,
+                None => break,
+            }
+        }
+        Ok(current)
+    }
+
+    /// Resolves the branch `steps` branches downstack from the given branch.
+    ///
+    /// Resolution stops early at the trunk branch.
+    pub fn resolve_downstack(&self, branch_name: &str, steps: usize) -> StResult<String> {
+        let mut current = branch_name.to_string();
+        for _ in 0..steps {
+            let parent = self
+                .tree
+                .get(&current)
+                .ok_or_else(|| StError::BranchNotTracked(current.to_string()))?
+                .parent
+                .clone();
+            match parent {
+                Some(parent) => current = parent,
+                None => break,
+            }
+        }
+        Ok(current)
+    }
+
+    /// Resolves the first branch above the trunk branch within the stack of the given branch.
+    ///
+    /// If the given branch is the trunk branch, the user is prompted to select a stack when
+    /// there are multiple.
+    ///
+    /// ## Returns
+    /// - `Some(branch)` - The bottom branch of the stack.
+    /// - `None` - The given branch is trunk, and no stacks exist.
+    pub fn resolve_bottom(&self, branch_name: &str) -> StResult<Option<String>> {
+        if branch_name == self.tree.trunk_name {
+            return self.resolve_child(branch_name);
+        }
+
+        let mut current = branch_name.to_string();
+        loop {
+            let parent = self
+                .tree
+                .get(&current)
+                .ok_or_else(|| StError::BranchNotTracked(current.to_string()))?
+                .parent
+                .clone();
+            match parent {
+                Some(parent) if parent != self.tree.trunk_name => current = parent,
+                _ => return Ok(Some(current)),
+            }
+        }
+    }
+
+    /// Checks out a tracked branch, printing a message describing the move.
+    pub fn checkout_tracked_branch(&self, branch_name: &str) -> StResult<()> {
+        if self.tree.get(branch_name).is_none() {
+            return Err(StError::BranchNotTracked(branch_name.to_string()));
+        }
+
+        if self.repository.current_branch_name()? == branch_name {
+            println!("Already on branch `{}`.", Color::Blue.paint(branch_name));
+            return Ok(());
+        }
+
+        self.repository.checkout_branch(branch_name)?;
+        println!("Checked out branch `{}`.", Color::Blue.paint(branch_name));
+        Ok(())
+    }
+
+    /// Returns the commits that belong to the given branch, ordered from oldest to newest. A
+    /// branch's commits are those between its parent [git2::Oid] cache and its `HEAD` commit.
+    ///
+    /// Returns an error if the branch needs to be restacked or contains merge commits, which
+    /// branches restacked with [crate::config::RestackStrategy::Merge] do. A merge commit brings
+    /// in commits of the parent that are not reachable from the parent [git2::Oid] cache, so the
+    /// branch's own commits cannot be told apart from them.
+    pub fn branch_commits(&self, branch_name: &str) -> StResult<Vec<Oid>> {
+        if self.needs_restack(branch_name)? {
+            return Err(StError::NeedsRestack(branch_name.to_string()));
+        }
+
+        let branch = self
+            .tree
+            .get(branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(branch_name.to_string()))?;
+        let parent_oid_cache = branch
+            .parent_oid_cache
+            .as_ref()
+            .ok_or(StError::MissingParentOidCache)?;
+        let head = self
+            .repository
+            .find_branch(branch_name, BranchType::Local)?
+            .get()
+            .target()
+            .ok_or(StError::BranchUnavailable)?;
+
+        let mut revwalk = self.repository.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+        revwalk.push(head)?;
+        revwalk.hide(Oid::from_str(parent_oid_cache)?)?;
+
+        revwalk
+            .map(|oid| {
+                let oid = oid?;
+                if self.repository.find_commit(oid)?.parent_count() > 1 {
+                    return Err(StError::MergeCommitsUnsupported(branch_name.to_string()));
+                }
+                Ok(oid)
+            })
+            .collect()
+    }
+
     /// Returns whether or not a given branch needs to be restacked onto its parent.
+    ///
+    /// A branch does not need to be restacked if its parent's `HEAD` commit is equal to its
+    /// parent [git2::Oid] cache, or is already an ancestor of the branch, as is the case when the
+    /// branch was rebased outside of `st`.
     pub fn needs_restack(&self, branch_name: &str) -> StResult<bool> {
         let branch = self
             .tree
@@ -80,21 +239,173 @@ impl StContext<'_> {
             .as_ref()
             .ok_or(StError::MissingParentOidCache)?;
 
-        // If the parent oid cache is invalid, or the parent needs to be restacked, then the branch
-        // needs to be restacked.
-        Ok(&parent_oid.to_string() != parent_oid_cache || self.needs_restack(parent_name)?)
+        // If the parent oid cache is invalid and the parent's `HEAD` is not contained in the
+        // branch, or the parent needs to be restacked, then the branch needs to be restacked.
+        let is_stale = &parent_oid.to_string() != parent_oid_cache
+            && !self.is_parent_contained(branch_name, parent_oid)?;
+        Ok(is_stale || self.needs_restack(parent_name)?)
     }
 
-    /// Performs a restack of the active stack.
-    pub fn restack(&mut self) -> StResult<()> {
-        // Discover the current stack.
-        let stack = self.discover_stack()?;
+    /// Refreshes the parent [git2::Oid] cache of every branch whose parent's `HEAD` commit is
+    /// already an ancestor of the branch, such as after a `git rebase` or `git pull --rebase`
+    /// performed outside of `st`.
+    pub fn refresh_parent_oid_caches(&mut self) -> StResult<()> {
+        let branch_names = self.tree.branches.keys().cloned().collect::<Vec<_>>();
+        for branch_name in branch_names {
+            let Some(parent_name) = self.tree.get(&branch_name).and_then(|b| b.parent.clone())
+            else {
+                continue;
+            };
 
-        // Rebase each branch onto its parent.
-        for (i, branch) in stack.iter().enumerate().skip(1) {
-            self.restack_branch(branch, &stack[i - 1])?;
-        }
+            // Parents that are not local branches are reported by `st doctor`.
+            let Ok(parent) = self.repository.find_branch(&parent_name, BranchType::Local) else {
+                continue;
+            };
+            let parent_oid = parent.get().target().ok_or(StError::BranchUnavailable)?;
+            let parent_oid_str = parent_oid.to_string();
 
+            let tracked_branch = self
+                .tree
+                .get(&branch_name)
+                .ok_or_else(|| StError::BranchNotTracked(branch_name.clone()))?;
+            if tracked_branch.parent_oid_cache.as_ref() == Some(&parent_oid_str)
+                || !self.is_parent_contained(&branch_name, parent_oid)?
+            {
+                continue;
+            }
+
+            self.tree
+                .get_mut(&branch_name)
+                .ok_or_else(|| StError::BranchNotTracked(branch_name.clone()))?
+                .parent_oid_cache = Some(parent_oid_str);
+        }
         Ok(())
     }
+
+    /// Returns whether or not the passed parent commit is reachable from the branch's `HEAD`.
+    fn is_parent_contained(&self, branch_name: &str, parent_oid: Oid) -> StResult<bool> {
+        let head = self
+            .repository
+            .find_branch(branch_name, BranchType::Local)?
+            .get()
+            .target()
+            .ok_or(StError::BranchUnavailable)?;
+        Ok(head == parent_oid || self.repository.graph_descendant_of(head, parent_oid)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::{RepoConfig, RestackStrategy},
+        errors::StError,
+        test_utils::TestRepo,
+    };
+
+    #[test]
+    fn merge_restacked_branch_is_rejected() {
+        let test_repo = TestRepo::new();
+        let mut repo_cfg = RepoConfig::default();
+        repo_cfg.restack.strategy = RestackStrategy::Merge;
+        let mut ctx = test_repo.ctx_with(repo_cfg);
+        let a_head = test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        assert_eq!(ctx.branch_commits("a").unwrap(), [a_head]);
+
+        // Merging `main` into `a` leaves a merge commit on top of it.
+        test_repo.checkout("main");
+        test_repo.commit("main.txt", "main\n", "main");
+        ctx.restack_branch("a", "main").unwrap();
+        assert_eq!(
+            test_repo
+                .repo
+                .find_commit(test_repo.head("a"))
+                .unwrap()
+                .parent_count(),
+            2
+        );
+        assert!(matches!(
+            ctx.branch_commits("a"),
+            Err(StError::MergeCommitsUnsupported(branch)) if branch == "a"
+        ));
+    }
+
+    #[test]
+    fn parent_rebased_outside_st_is_contained() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        let a_head = test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        test_repo.create_branch(&mut ctx, "b", "b.txt", "b\n");
+        test_repo.checkout("a");
+        let a_head_2 = test_repo.commit("a.txt", "a 2\n", "a 2");
+        assert!(ctx.needs_restack("b").unwrap());
+
+        // Rebasing `b` with git leaves its parent oid cache stale, but `b` contains `a`.
+        test_repo.git(&["rebase", "a", "b"]);
+        assert!(!ctx.needs_restack("b").unwrap());
+        assert_eq!(
+            ctx.tree.get("b").unwrap().parent_oid_cache,
+            Some(a_head.to_string())
+        );
+
+        ctx.refresh_parent_oid_caches().unwrap();
+        assert_eq!(
+            ctx.tree.get("b").unwrap().parent_oid_cache,
+            Some(a_head_2.to_string())
+        );
+    }
+
+    #[test]
+    fn rewritten_parent_needs_restack() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        let a_head = test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        test_repo.create_branch(&mut ctx, "b", "b.txt", "b\n");
+        test_repo.checkout("a");
+        test_repo.write("a.txt", "amended\n");
+        test_repo.git(&["commit", "-a", "--amend", "--no-edit"]);
+        assert!(ctx.needs_restack("b").unwrap());
+
+        // The amended parent is not contained in `b`, so its cache is left for the restack.
+        ctx.refresh_parent_oid_caches().unwrap();
+        assert_eq!(
+            ctx.tree.get("b").unwrap().parent_oid_cache,
+            Some(a_head.to_string())
+        );
+        assert!(ctx.needs_restack("b").unwrap());
+    }
+
+    #[test]
+    fn resolve_navigation() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        ctx.tree.insert("main", "0", "a").unwrap();
+        ctx.tree.insert("a", "1", "b").unwrap();
+        ctx.tree.insert("b", "2", "c").unwrap();
+
+        assert_eq!(ctx.resolve_child("a").unwrap().as_deref(), Some("b"));
+        assert_eq!(ctx.resolve_child("c").unwrap(), None);
+
+        // Moving up or down stops at the tip of the stack and at trunk.
+        assert_eq!(ctx.resolve_upstack("a", 0).unwrap(), "a");
+        assert_eq!(ctx.resolve_upstack("a", 2).unwrap(), "c");
+        assert_eq!(ctx.resolve_upstack("main", usize::MAX).unwrap(), "c");
+        assert_eq!(ctx.resolve_downstack("c", 1).unwrap(), "b");
+        assert_eq!(ctx.resolve_downstack("c", 5).unwrap(), "main");
+
+        assert_eq!(ctx.resolve_bottom("c").unwrap().as_deref(), Some("a"));
+        assert_eq!(ctx.resolve_bottom("a").unwrap().as_deref(), Some("a"));
+        assert_eq!(ctx.resolve_bottom("main").unwrap().as_deref(), Some("a"));
+
+        assert!(matches!(
+            ctx.resolve_downstack("untracked", 1),
+            Err(StError::BranchNotTracked(branch)) if branch == "untracked"
+        ));
+    }
+
+    #[test]
+    fn resolve_bottom_without_stacks() {
+        let test_repo = TestRepo::new();
+        let ctx = test_repo.ctx();
+        assert_eq!(ctx.resolve_bottom("main").unwrap(), None);
+    }
 }
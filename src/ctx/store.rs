@@ -0,0 +1,138 @@
+//! Versioned persistence of the [StackTree] in the store file, with migrations from older
+//! layouts.
+//!
+//! Every document `st` persists shares the same versioned envelope: the store file, the metadata
+//! ref, snapshots, the journal, and the pending restack plan. A migration upgrades the
+//! [StackTree] layout wherever it appears within a document.
+
+use super::write_atomic;
+use crate::{
+    errors::{StError, StResult},
+    tree::StackTree,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{ffi::OsString, path::Path};
+use toml::{Table, Value};
+
+/// The version of the layout written by this version of `st`.
+pub(crate) const STORE_VERSION: i64 = 1;
+
+/// The key holding the layout version within a document.
+const VERSION_KEY: &str = "version";
+
+/// A migration, which upgrades a document from the layout version at its index to the next one.
+type Migration = fn(&mut Table) -> StResult<()>;
+
+/// The migrations between layout versions, in order.
+const MIGRATIONS: [Migration; STORE_VERSION as usize] = [migrate_v0_to_v1];
+
+/// Reads the [StackTree] from the store at `path`, migrating it to the current layout if it was
+/// written by an older version of `st`.
+///
+/// Before a migrated store is written back, the original is kept alongside it as
+/// `<store>.v<version>.bak`.
+pub(crate) fn read_store(path: &Path) -> StResult<StackTree> {
+    let contents = std::fs::read_to_string(path)?;
+    let (tree, version) = decode_versioned(&contents)?;
+
+    if version < STORE_VERSION {
+        let mut backup_path = OsString::from(path.as_os_str());
+        backup_path.push(format!(".v{}.bak", version));
+        std::fs::write(&backup_path, &contents)?;
+        write_store(path, &tree)?;
+    }
+    Ok(tree)
+}
+
+/// Writes the [StackTree] to the store at `path` in the current layout.
+pub(crate) fn write_store(path: &Path, tree: &StackTree) -> StResult<()> {
+    write_atomic(path, &encode_versioned(tree)?)
+}
+
+/// Serializes a document in the current layout.
+pub(crate) fn encode_versioned<T: Serialize>(value: &T) -> StResult<String> {
+    let mut table = Table::try_from(value)?;
+    table.insert(VERSION_KEY.to_string(), Value::Integer(STORE_VERSION));
+    Ok(toml::to_string_pretty(&table)?)
+}
+
+/// Deserializes a document of any supported layout version, migrating it to the current one.
+///
+/// ## Returns
+/// - `Result<(T, i64)>` - The document, and the layout version it was in.
+pub(crate) fn decode_versioned<T: DeserializeOwned>(contents: &str) -> StResult<(T, i64)> {
+    let mut table = contents.parse::<Table>()?;
+
+    // Documents written before the layout was versioned carry no version key.
+    let version = match table.remove(VERSION_KEY) {
+        Some(Value::Integer(version)) => version,
+        Some(_) => {
+            return Err(StError::DecodingError(
+                "Layout version must be an integer.".to_string(),
+            ))
+        }
+        None => 0,
+    };
+    if version > STORE_VERSION {
+        return Err(StError::StoreVersionUnsupported(version, STORE_VERSION));
+    }
+
+    for migration in MIGRATIONS.iter().skip(version.max(0) as usize) {
+        migration(&mut table)?;
+    }
+    Ok((Value::Table(table).try_into()?, version))
+}
+
+/// Unversioned documents share the layout of version 1, which only adds the version key.
+fn migrate_v0_to_v1(_: &mut Table) -> StResult<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_versioned, encode_versioned, STORE_VERSION};
+    use crate::{ctx::Journal, errors::StError, tree::StackTree};
+
+    #[test]
+    fn unversioned_store_is_migrated() {
+        let store = "trunk-name = \"main\"\n[branches.main]\nname = \"main\"\nchildren = []\n";
+        let (tree, version): (StackTree, _) = decode_versioned(store).unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(tree, StackTree::new("main".to_string()));
+    }
+
+    #[test]
+    fn store_round_trips_with_version() {
+        let tree = StackTree::new("main".to_string());
+        let (decoded, version): (StackTree, _) =
+            decode_versioned(&encode_versioned(&tree).unwrap()).unwrap();
+        assert_eq!(version, STORE_VERSION);
+        assert_eq!(decoded, tree);
+    }
+
+    #[test]
+    fn newer_store_is_refused() {
+        let store = format!("version = {}\ntrunk-name = \"main\"\n", STORE_VERSION + 1);
+        assert!(matches!(
+            decode_versioned::<StackTree>(&store),
+            Err(StError::StoreVersionUnsupported(_, _))
+        ));
+    }
+
+    #[test]
+    fn journal_shares_versioned_envelope() {
+        let journal = Journal::default();
+        let encoded = encode_versioned(&journal).unwrap();
+        assert!(encoded.contains(&format!("version = {}", STORE_VERSION)));
+        assert_eq!(
+            decode_versioned::<Journal>(&encoded).unwrap(),
+            (journal, STORE_VERSION)
+        );
+
+        let newer = format!("version = {}\n", STORE_VERSION + 1);
+        assert!(matches!(
+            decode_versioned::<Journal>(&newer),
+            Err(StError::StoreVersionUnsupported(_, _))
+        ));
+    }
+}
@@ -1,6 +1,6 @@
 //! Errors for the `st` application.
 
-use crate::{config::StConfigError, git::GitCommandError};
+use crate::{config::StConfigError, constants::QUOTE_CHAR, git::GitCommandError};
 use nu_ansi_term::Color;
 use thiserror::Error;
 
@@ -19,9 +19,31 @@ pub enum StError {
     /// The branch is already tracked with `st`.
     #[error("Branch `{}` is already tracked with `{}`.", Color::Blue.paint(.0), Color::Blue.paint("st"))]
     BranchAlreadyTracked(String),
+    /// A local branch with the name already exists.
+    #[error("Branch `{}` already exists.", Color::Blue.paint(.0))]
+    BranchAlreadyExists(String),
     /// Cannot delete the trunk branch.
     #[error("Cannot delete the trunk branch.")]
     CannotDeleteTrunkBranch,
+    /// Cannot modify the trunk branch.
+    #[error("Cannot modify the trunk branch.")]
+    CannotModifyTrunkBranch,
+    /// Cannot fold a branch into the trunk branch.
+    #[error(
+        "Cannot fold branch `{}` into the trunk branch. Merge its pull request instead.",
+        Color::Green.paint(.0)
+    )]
+    CannotFoldIntoTrunk(String),
+    /// Cannot move the trunk branch.
+    #[error("Cannot move the trunk branch.")]
+    CannotMoveTrunkBranch,
+    /// Cannot move a branch onto itself or one of its descendants.
+    #[error(
+        "Cannot move branch `{}` onto `{}`, which is the branch itself or one of its descendants.",
+        Color::Green.paint(.0),
+        Color::Yellow.paint(.1)
+    )]
+    InvalidMoveTarget(String, String),
     /// A branch needs to be restacked.
     #[error(
         "Branch `{}` needs to be restacked before continuing. Restack with `{}` before continuing.",
@@ -29,9 +51,83 @@ pub enum StError {
         Color::Blue.paint("st restack")
     )]
     NeedsRestack(String),
+    /// A restack stopped due to conflicts.
+    #[error(
+        "Restacking branch `{}` stopped due to conflicts. Resolve them, stage the changes with `{}`, and run `{}`. To return the stack to its original state, run `{}`.",
+        Color::Green.paint(.0),
+        Color::Blue.paint("git add"),
+        Color::Blue.paint("st continue"),
+        Color::Blue.paint("st abort")
+    )]
+    RestackConflict(String),
+    /// A restack is already in progress.
+    #[error(
+        "A restack is already in progress. Run `{}` or `{}` before continuing.",
+        Color::Blue.paint("st continue"),
+        Color::Blue.paint("st abort")
+    )]
+    RestackInProgress,
+    /// No restack is in progress.
+    #[error("No restack is in progress.")]
+    NoRestackInProgress,
+    /// The branch contains merge commits, which are not supported by the command. Branches
+    /// restacked with the `merge` strategy contain them.
+    #[error(
+        "Branch `{}` contains merge commits, which this command cannot rewrite. Rebase the branch onto its parent to linearize its history first.",
+        Color::Green.paint(.0)
+    )]
+    MergeCommitsUnsupported(String),
+    /// The stack forks, and cannot be reordered.
+    #[error(
+        "Branch `{}` has children outside of the current stack. Only linear stacks can be reordered.",
+        Color::Green.paint(.0)
+    )]
+    NonLinearStack(String),
+    /// The reordered stack does not contain exactly the branches of the original stack.
+    #[error("The new order must list each branch of the stack exactly once.")]
+    InvalidReorder,
+    /// The branch does not contain enough changes to split.
+    #[error(
+        "Branch `{}` does not contain enough {} to split.",
+        Color::Green.paint(.0),
+        .1
+    )]
+    NothingToSplit(String, &'static str),
+    /// Some staged hunks could not be attributed to a single branch in the stack.
+    #[error(
+        "Could not determine which branch in the stack the following hunks belong to:\n{}",
+        .0.iter().map(|h| format!("{} {}", QUOTE_CHAR, h)).collect::<Vec<_>>().join("\n")
+    )]
+    AmbiguousHunks(Vec<String>),
+    /// The hunks attributed to a branch conflict with its contents.
+    #[error(
+        "The hunks attributed to branch `{}` do not apply cleanly to it. Commit them manually instead.",
+        Color::Green.paint(.0)
+    )]
+    AbsorbConflict(String),
+    /// A snapshot with the given name already exists.
+    #[error(
+        "Snapshot `{}` already exists. Pass `{}` to overwrite it.",
+        Color::Blue.paint(.0),
+        Color::Blue.paint("--force")
+    )]
+    SnapshotAlreadyExists(String),
+    /// No snapshot with the given name exists.
+    #[error(
+        "Snapshot `{}` not found. List snapshots with `{}`.",
+        Color::Blue.paint(.0),
+        Color::Blue.paint("st snapshot list")
+    )]
+    SnapshotNotFound(String),
+    /// The snapshot name is not a valid reference name.
+    #[error("`{}` is not a valid snapshot name.", Color::Blue.paint(.0))]
+    InvalidSnapshotName(String),
     /// A commit message is required with --all or --update
     #[error("Commit message is required with --all or --update")]
     CommitMessageRequired,
+    /// There are no staged changes to commit.
+    #[error("No changes are staged. Stage changes or pass `{}` to stage them.", Color::Blue.paint("--all"))]
+    NothingToCommit,
     /// The working tree is dirty.
     #[error("Working tree is dirty. Please commit or stash changes before continuing.")]
     WorkingTreeDirty,
@@ -41,19 +137,45 @@ pub enum StError {
     /// A generic decoding error occurred.
     #[error("Decoding error: {}", .0)]
     DecodingError(String),
+    /// Two stack trees with different trunk branches cannot be merged.
+    #[error(
+        "Cannot merge a stack on top of `{}` with a stack on top of `{}`.",
+        Color::Blue.paint(.0),
+        Color::Blue.paint(.1)
+    )]
+    TrunkMismatch(String, String),
+    /// The store, metadata ref, a snapshot, or the journal was written by a newer version of
+    /// `st`.
+    #[error(
+        "The stack metadata uses layout version {}, but this version of `{}` only supports up to version {}. Upgrade `{}` to continue.",
+        .0,
+        Color::Blue.paint("st"),
+        .1,
+        Color::Blue.paint("st")
+    )]
+    StoreVersionUnsupported(i64, i64),
+    /// Another `st` process holds the lock on the store.
+    #[error(
+        "Another `{}` process is using this repository. Wait for it to finish, and try again.",
+        Color::Blue.paint("st")
+    )]
+    StoreLocked,
 
     // ---- [ `st` application errors (remote) ] ----
     /// A remote pull request could not be found.
     #[error("Remote pull request not found.")]
     PullRequestNotFound,
+    /// A pull request is closed or merged.
+    #[error("Pull request #{} is no longer open.", .0)]
+    PullRequestClosed(u64),
+    /// A pull request's head branch lives in a fork.
+    #[error("Pull request #{} was opened from a fork, and its branch cannot be fetched from this repository's remote.", .0)]
+    PullRequestFromFork(u64),
 
     // ---- [ Git Errors ] ----
     /// `st` mused be run within a git repository.
     #[error("`{}` must be used within a git repository.", Color::Blue.paint("st"))]
     NotAGitRepository,
-    /// The git repository root could not be found.
-    #[error("Git repository root could not be found.")]
-    GitRepositoryRootNotFound,
     /// Remote not found.
     #[error("Remote `{}` not found.", Color::Blue.paint(.0))]
     RemoteNotFound(String),
@@ -71,9 +193,9 @@ pub enum StError {
     /// A `git` command error occurred.
     #[error(transparent)]
     GitCommandError(#[from] GitCommandError),
-    /// An [octocrab::Error] occurred.
+    /// An [octocrab::Error] occurred. The error is boxed, as it is much larger than the others.
     #[error("🐙 octocrab error: {:?}", .0)]
-    OctocrabError(#[from] octocrab::Error),
+    OctocrabError(Box<octocrab::Error>),
     /// An [inquire::InquireError] occurred.
     #[error("🔍 inquire error: {}", .0)]
     InquireError(#[from] inquire::InquireError),
@@ -91,5 +213,11 @@ pub enum StError {
     TomlDecodingError(#[from] toml::de::Error),
 }
 
+impl From<octocrab::Error> for StError {
+    fn from(e: octocrab::Error) -> Self {
+        Self::OctocrabError(Box::new(e))
+    }
+}
+
 /// A short-hand [Result] type alias for the [StError].
 pub type StResult<T> = Result<T, StError>;
@@ -2,10 +2,19 @@
 
 use crate::constants::QUOTE_CHAR;
 use git2::{
-    build::CheckoutBuilder, Branch, BranchType, ErrorClass, ErrorCode, Repository, StatusOptions,
+    build::CheckoutBuilder, ApplyOptions, Branch, BranchType, Diff, DiffDelta, ErrorClass,
+    ErrorCode, Index, IndexAddOption, Oid, Patch, Repository, RepositoryState, Sort, StatusOptions,
+    Tree,
 };
 use nu_ansi_term::Color::Red;
-use std::{env, process::Command};
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    env,
+    fmt::Display,
+    path::PathBuf,
+    process::Command,
+};
 use thiserror::Error;
 
 /// Returns the repository for the current working directory, and [None] if
@@ -22,7 +31,7 @@ pub trait RepositoryExt {
     ///
     /// ## Returns
     /// - `Result<Branch>` - The current [Branch], or an error.
-    fn current_branch(&self) -> Result<Branch, git2::Error>;
+    fn current_branch(&self) -> Result<Branch<'_>, git2::Error>;
 
     /// Returns the name of the current [Branch].
     ///
@@ -36,6 +45,15 @@ pub trait RepositoryExt {
     /// - `Result<bool>` - True if the working tree is clean, false otherwise.
     fn is_working_tree_clean(&self) -> Result<bool, git2::Error>;
 
+    /// Stages changes in the working tree to the index.
+    ///
+    /// ## Takes
+    /// - `tracked_only` - Whether to only stage changes to files that are already tracked.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - The result of the operation.
+    fn stage_changes(&self, tracked_only: bool) -> Result<(), git2::Error>;
+
     /// Checks out a branch with the given `branch_name`.
     ///
     /// ## Takes
@@ -46,6 +64,33 @@ pub trait RepositoryExt {
     /// - `Result<()>` - The result of the operation.
     fn checkout_branch(&self, branch_name: &str) -> Result<(), git2::Error>;
 
+    /// Checks out a branch with the given `branch_name`, discarding any changes in the working
+    /// tree and index.
+    ///
+    /// ## Takes
+    /// - `branch_name` - The name of the branch to checkout.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - The result of the operation.
+    fn force_checkout_branch(&self, branch_name: &str) -> Result<(), git2::Error>;
+
+    /// Returns the common git directory of the repository, which is shared by all of its
+    /// worktrees. For the main worktree and bare repositories, this is the git directory itself.
+    ///
+    /// ## Returns
+    /// - `PathBuf` - The common git directory.
+    fn common_dir(&self) -> PathBuf;
+
+    /// Returns the working directory of another worktree that has the branch checked out.
+    ///
+    /// ## Takes
+    /// - `branch_name` - The name of the branch to look for.
+    ///
+    /// ## Returns
+    /// - `Result<Some(PathBuf)>` - The working directory of the other worktree.
+    /// - `Result<None>` - If no other worktree has the branch checked out.
+    fn branch_worktree(&self, branch_name: &str) -> Result<Option<PathBuf>, git2::Error>;
+
     /// Set the target of a branch to its upstream [git2::Reference].
     ///
     /// ## Takes
@@ -59,15 +104,70 @@ pub trait RepositoryExt {
         remote_name: &str,
     ) -> Result<(), git2::Error>;
 
+    /// Replays the commits reachable from `head` but not from `upstream` onto `onto`, entirely in
+    /// memory. Merge commits are dropped, as are commits whose changes are already present.
+    ///
+    /// No references, the index, or the working tree are modified; the replayed commits are only
+    /// written to the object database.
+    ///
+    /// ## Takes
+    /// - `head` - The head commit of the range to replay.
+    /// - `upstream` - The commit to exclude, along with its ancestors, from the range.
+    /// - `onto` - The commit to replay the range onto.
+    ///
+    /// ## Returns
+    /// - `Result<ReplayOutcome>` - The outcome of the replay, or an error.
+    fn replay_commits(
+        &self,
+        head: Oid,
+        upstream: Oid,
+        onto: Oid,
+    ) -> Result<ReplayOutcome, git2::Error>;
+
+    /// Applies a subset of the hunks within a [Diff] to a [Tree], writing the resulting tree to
+    /// the object database.
+    ///
+    /// ## Takes
+    /// - `tree` - The tree to apply the hunks to.
+    /// - `diff` - The diff containing the hunks.
+    /// - `hunks` - The indices of the hunks to apply, as returned by [diff_hunks].
+    ///
+    /// ## Returns
+    /// - `Result<Oid>` - The [Oid] of the resulting tree, or an error.
+    fn apply_hunks(
+        &self,
+        tree: &Tree<'_>,
+        diff: &Diff<'_>,
+        hunks: &HashSet<usize>,
+    ) -> Result<Oid, git2::Error>;
+
     /// Rebases a branch onto another branch.
     ///
     /// ## Takes
     /// - `branch_name` - The name of the branch to rebase.
     /// - `onto` - The name of the branch to rebase onto.
+    /// - `upstream` - The upstream commit of the branch. If [None], `onto` is used as the upstream.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - The result of the operation.
+    fn rebase_branch_onto(
+        &self,
+        branch_name: &str,
+        onto: &str,
+        upstream: Option<&str>,
+    ) -> Result<(), GitCommandError>;
+
+    /// Returns whether or not a rebase is in progress.
+    ///
+    /// ## Returns
+    /// - `bool` - True if a rebase is in progress, false otherwise.
+    fn is_rebase_in_progress(&self) -> bool;
+
+    /// Continues a rebase in progress, keeping the existing commit messages.
     ///
     /// ## Returns
     /// - `Result<()>` - The result of the operation.
-    fn rebase_branch_onto(&self, branch_name: &str, onto: &str) -> Result<(), GitCommandError>;
+    fn continue_rebase(&self) -> Result<(), GitCommandError>;
 
     /// Aborts a rebase in progress.
     ///
@@ -75,6 +175,66 @@ pub trait RepositoryExt {
     /// - `Result<()>` - The result of the operation.
     fn abort_rebase(&self) -> Result<(), GitCommandError>;
 
+    /// Stashes the changes in the index and working tree, leaving both clean.
+    ///
+    /// ## Takes
+    /// - `message` - The message of the stash entry.
+    ///
+    /// ## Returns
+    /// - `Result<Oid>` - The stash commit, whose second parent holds the index.
+    fn stash_changes(&self, message: &str) -> Result<Oid, GitCommandError>;
+
+    /// Drops the most recent stash entry.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - The result of the operation.
+    fn drop_stash(&self) -> Result<(), GitCommandError>;
+
+    /// Merges a commit into another in memory, without touching the working tree or any refs.
+    ///
+    /// ## Takes
+    /// - `head` - The commit to merge into, which becomes the first parent of the merge commit.
+    /// - `other` - The commit to merge.
+    /// - `message` - The message of the merge commit.
+    ///
+    /// ## Returns
+    /// - `Result<ReplayOutcome>` - The outcome of the merge, or an error.
+    fn merge_commit(
+        &self,
+        head: Oid,
+        other: Oid,
+        message: &str,
+    ) -> Result<ReplayOutcome, git2::Error>;
+
+    /// Merges a branch into another branch, stopping to let the user resolve conflicts.
+    ///
+    /// ## Takes
+    /// - `branch_name` - The name of the branch to merge into.
+    /// - `other_name` - The name of the branch to merge.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - The result of the operation.
+    fn merge_branch_into(&self, branch_name: &str, other_name: &str)
+        -> Result<(), GitCommandError>;
+
+    /// Returns whether or not a merge is in progress.
+    ///
+    /// ## Returns
+    /// - `bool` - True if a merge is in progress, false otherwise.
+    fn is_merge_in_progress(&self) -> bool;
+
+    /// Continues a merge in progress, keeping the default merge commit message.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - The result of the operation.
+    fn continue_merge(&self) -> Result<(), GitCommandError>;
+
+    /// Aborts a merge in progress.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - The result of the operation.
+    fn abort_merge(&self) -> Result<(), GitCommandError>;
+
     /// Pushes a branch to a registered remote.
     ///
     /// ## Takes
@@ -91,6 +251,63 @@ pub trait RepositoryExt {
         force: bool,
     ) -> Result<(), GitCommandError>;
 
+    /// Deletes a branch from a registered remote.
+    ///
+    /// ## Takes
+    /// - `branch_name` - The name of the branch to delete.
+    /// - `remote_name` - The name of the remote to delete the branch from.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - The result of the operation.
+    fn delete_remote_branch(
+        &self,
+        branch_name: &str,
+        remote_name: &str,
+    ) -> Result<(), GitCommandError>;
+
+    /// Pushes a refspec to a registered remote.
+    ///
+    /// ## Takes
+    /// - `refspec` - The refspec to push.
+    /// - `remote_name` - The name of the remote to push to.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - The result of the operation.
+    fn push_refspec(&self, refspec: &str, remote_name: &str) -> Result<(), GitCommandError>;
+
+    /// Fetches a refspec from a registered remote.
+    ///
+    /// ## Takes
+    /// - `refspec` - The refspec to fetch.
+    /// - `remote_name` - The name of the remote to fetch from.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - The result of the operation.
+    fn fetch_refspec(&self, refspec: &str, remote_name: &str) -> Result<(), GitCommandError>;
+
+    /// Looks up a ref on a registered remote, without fetching it.
+    ///
+    /// ## Takes
+    /// - `ref_name` - The full name of the ref, e.g. `refs/heads/main`.
+    /// - `remote_name` - The name of the remote to look the ref up on.
+    ///
+    /// ## Returns
+    /// - `Result<Some(Oid)>` - The commit the ref points to on the remote.
+    /// - `Result<None>` - If the remote has no such ref.
+    fn remote_ref(&self, ref_name: &str, remote_name: &str)
+        -> Result<Option<Oid>, GitCommandError>;
+
+    /// Fetches a branch from a registered remote into its remote-tracking branch, without
+    /// touching the local branch.
+    ///
+    /// ## Takes
+    /// - `branch_name` - The name of the branch to fetch.
+    /// - `remote_name` - The name of the remote to fetch from.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - The result of the operation.
+    fn fetch_branch(&self, branch_name: &str, remote_name: &str) -> Result<(), GitCommandError>;
+
     /// Pulls a branch from a registered remote.
     ///
     /// ## Takes
@@ -103,7 +320,7 @@ pub trait RepositoryExt {
 }
 
 impl RepositoryExt for Repository {
-    fn current_branch(&self) -> Result<Branch, git2::Error> {
+    fn current_branch(&self) -> Result<Branch<'_>, git2::Error> {
         let head = self.head()?;
         let branch = self.find_branch(
             head.name()
@@ -143,6 +360,16 @@ impl RepositoryExt for Repository {
         Ok(statuses.is_empty())
     }
 
+    fn stage_changes(&self, tracked_only: bool) -> Result<(), git2::Error> {
+        let mut index = self.index()?;
+        if tracked_only {
+            index.update_all(vec!["*"], None)?;
+        } else {
+            index.add_all(vec!["*"], IndexAddOption::DEFAULT, None)?;
+        }
+        index.write()
+    }
+
     fn checkout_branch(&self, branch_name: &str) -> Result<(), git2::Error> {
         if !self.is_working_tree_clean()? {
             return Err(git2::Error::new(
@@ -151,12 +378,62 @@ impl RepositoryExt for Repository {
                 "Working tree is not clean. Commit or stash changes before checking out another branch.",
             ));
         }
+        if let Some(path) = self.branch_worktree(branch_name)? {
+            return Err(git2::Error::new(
+                ErrorCode::Locked,
+                ErrorClass::Checkout,
+                format!(
+                    "Branch `{}` is already checked out in the worktree at `{}`.",
+                    branch_name,
+                    path.display()
+                ),
+            ));
+        }
+
+        self.force_checkout_branch(branch_name)
+    }
 
+    fn force_checkout_branch(&self, branch_name: &str) -> Result<(), git2::Error> {
         self.set_head(format!("refs/heads/{}", branch_name).as_str())?;
         self.checkout_head(Some(CheckoutBuilder::new().force()))?;
         Ok(())
     }
 
+    fn common_dir(&self) -> PathBuf {
+        // Linked worktrees point at the common directory through the `commondir` file within
+        // their own git directory, usually with a path relative to it.
+        let git_dir = self.path();
+        match std::fs::read_to_string(git_dir.join("commondir")) {
+            Ok(common_dir) if self.is_worktree() => git_dir.join(common_dir.trim_end()),
+            _ => git_dir.to_path_buf(),
+        }
+    }
+
+    fn branch_worktree(&self, branch_name: &str) -> Result<Option<PathBuf>, git2::Error> {
+        // The main worktree lives in the common directory, and linked worktrees are registered
+        // within it.
+        let mut worktrees = vec![Repository::open(self.common_dir())?];
+        for name in self.worktrees()?.iter().flatten() {
+            let worktree = self.find_worktree(name)?;
+            if worktree.validate().is_ok() {
+                worktrees.push(Repository::open_from_worktree(&worktree)?);
+            }
+        }
+
+        let own_path = self.path().canonicalize().ok();
+        let ref_name = format!("refs/heads/{}", branch_name);
+        for worktree in worktrees {
+            if worktree.is_bare() || worktree.path().canonicalize().ok() == own_path {
+                continue;
+            }
+            let head = worktree.find_reference("HEAD")?;
+            if head.symbolic_target() == Some(ref_name.as_str()) {
+                return Ok(worktree.workdir().map(|p| p.to_path_buf()));
+            }
+        }
+        Ok(None)
+    }
+
     fn set_target_to_upstream_ref(
         &self,
         branch_name: &str,
@@ -178,21 +455,201 @@ impl RepositoryExt for Repository {
         self.checkout_branch(branch_name)
     }
 
+    fn replay_commits(
+        &self,
+        head: Oid,
+        upstream: Oid,
+        onto: Oid,
+    ) -> Result<ReplayOutcome, git2::Error> {
+        // Collect the commits to replay, oldest first.
+        let mut revwalk = self.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+        revwalk.push(head)?;
+        revwalk.hide(upstream)?;
+
+        let mut current = self.find_commit(onto)?;
+        for oid in revwalk {
+            let commit = self.find_commit(oid?)?;
+            if commit.parent_count() > 1 {
+                continue;
+            }
+
+            // Cherry-pick the commit onto the current head of the replayed range.
+            let mut index = self.cherrypick_commit(&commit, &current, 0, None)?;
+            if index.has_conflicts() {
+                return Ok(ReplayOutcome::Conflict {
+                    commit: commit.id(),
+                    paths: conflict_paths(&index)?,
+                });
+            }
+
+            // Skip commits whose changes are already present.
+            let tree_oid = index.write_tree_to(self)?;
+            if tree_oid == current.tree_id() {
+                continue;
+            }
+
+            let tree = self.find_tree(tree_oid)?;
+            let committer = self
+                .signature()
+                .unwrap_or_else(|_| commit.committer().to_owned());
+            let new_oid = self.commit(
+                None,
+                &commit.author(),
+                &committer,
+                commit.message_raw().unwrap_or_default(),
+                &tree,
+                &[&current],
+            )?;
+            current = self.find_commit(new_oid)?;
+        }
+
+        Ok(ReplayOutcome::Complete(current.id()))
+    }
+
+    fn apply_hunks(
+        &self,
+        tree: &Tree<'_>,
+        diff: &Diff<'_>,
+        hunks: &HashSet<usize>,
+    ) -> Result<Oid, git2::Error> {
+        // Map each delta's path to the index of its first hunk, so that hunks can be identified
+        // as libgit2 walks the diff.
+        let all_hunks = diff_hunks(diff)?;
+        let mut delta_starts = HashMap::new();
+        for hunk in all_hunks.iter() {
+            delta_starts.entry(hunk.path.clone()).or_insert(hunk.index);
+        }
+
+        let cursor = Cell::new(0);
+        let mut opts = ApplyOptions::new();
+        opts.delta_callback(|delta| {
+            let Some(path) = delta.as_ref().and_then(delta_path) else {
+                return false;
+            };
+            let Some(&start) = delta_starts.get(&path) else {
+                return false;
+            };
+            cursor.set(start);
+
+            // Only apply deltas with at least one selected hunk.
+            all_hunks
+                .iter()
+                .filter(|h| h.path == path)
+                .any(|h| hunks.contains(&h.index))
+        });
+        opts.hunk_callback(|_| {
+            let index = cursor.get();
+            cursor.set(index + 1);
+            hunks.contains(&index)
+        });
+
+        let mut index = self.apply_to_tree(tree, diff, Some(&mut opts))?;
+        index.write_tree_to(self)
+    }
+
     fn rebase_branch_onto(
         &self,
         branch_name: &str,
         onto_name: &str,
+        upstream: Option<&str>,
     ) -> Result<(), GitCommandError> {
         // Check out the branch to rebase.
         self.checkout_branch(branch_name)?;
 
         // Cheat and shell out to git to rebase the branch. This is mainly because git2 doesn't
         // create rebase-todo files, etc., and it's easier to just shell out to git.
-        execute_git_command(&["rebase", onto_name], false)
+        match upstream {
+            Some(upstream) => {
+                execute_git_command(self, &["rebase", "--onto", onto_name, upstream], false)
+            }
+            None => execute_git_command(self, &["rebase", onto_name], false),
+        }
+    }
+
+    fn is_rebase_in_progress(&self) -> bool {
+        matches!(
+            self.state(),
+            RepositoryState::Rebase
+                | RepositoryState::RebaseInteractive
+                | RepositoryState::RebaseMerge
+        )
+    }
+
+    fn continue_rebase(&self) -> Result<(), GitCommandError> {
+        execute_git_command(
+            self,
+            &["-c", "core.editor=true", "rebase", "--continue"],
+            false,
+        )
     }
 
     fn abort_rebase(&self) -> Result<(), GitCommandError> {
-        execute_git_command(&["rebase", "--abort"], false)
+        execute_git_command(self, &["rebase", "--abort"], false)
+    }
+
+    fn stash_changes(&self, message: &str) -> Result<Oid, GitCommandError> {
+        execute_git_command(self, &["stash", "push", "-q", "-m", message], false)?;
+        Ok(self.refname_to_id("refs/stash")?)
+    }
+
+    fn drop_stash(&self) -> Result<(), GitCommandError> {
+        execute_git_command(self, &["stash", "drop", "-q"], false)
+    }
+
+    fn merge_commit(
+        &self,
+        head: Oid,
+        other: Oid,
+        message: &str,
+    ) -> Result<ReplayOutcome, git2::Error> {
+        let head_commit = self.find_commit(head)?;
+        let other_commit = self.find_commit(other)?;
+
+        let mut index = self.merge_commits(&head_commit, &other_commit, None)?;
+        if index.has_conflicts() {
+            return Ok(ReplayOutcome::Conflict {
+                commit: other,
+                paths: conflict_paths(&index)?,
+            });
+        }
+
+        let tree = self.find_tree(index.write_tree_to(self)?)?;
+        let signature = self.signature()?;
+        self.commit(
+            None,
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &[&head_commit, &other_commit],
+        )
+        .map(ReplayOutcome::Complete)
+    }
+
+    fn merge_branch_into(
+        &self,
+        branch_name: &str,
+        other_name: &str,
+    ) -> Result<(), GitCommandError> {
+        self.checkout_branch(branch_name)?;
+        execute_git_command(self, &["merge", "--no-edit", other_name], false)
+    }
+
+    fn is_merge_in_progress(&self) -> bool {
+        self.state() == RepositoryState::Merge
+    }
+
+    fn continue_merge(&self) -> Result<(), GitCommandError> {
+        execute_git_command(
+            self,
+            &["-c", "core.editor=true", "merge", "--continue"],
+            false,
+        )
+    }
+
+    fn abort_merge(&self) -> Result<(), GitCommandError> {
+        execute_git_command(self, &["merge", "--abort"], false)
     }
 
     fn push_branch(
@@ -206,13 +663,159 @@ impl RepositoryExt for Repository {
             args.push("--force");
         }
 
-        execute_git_command(args.as_slice(), false)
+        execute_git_command(self, args.as_slice(), false)
+    }
+
+    fn delete_remote_branch(
+        &self,
+        branch_name: &str,
+        remote_name: &str,
+    ) -> Result<(), GitCommandError> {
+        execute_git_command(self, &["push", remote_name, "--delete", branch_name], false)
+    }
+
+    fn push_refspec(&self, refspec: &str, remote_name: &str) -> Result<(), GitCommandError> {
+        execute_git_command(self, &["push", remote_name, refspec], false)
+    }
+
+    fn fetch_refspec(&self, refspec: &str, remote_name: &str) -> Result<(), GitCommandError> {
+        execute_git_command(self, &["fetch", remote_name, refspec], false)
+    }
+
+    fn remote_ref(
+        &self,
+        ref_name: &str,
+        remote_name: &str,
+    ) -> Result<Option<Oid>, GitCommandError> {
+        // `ls-remote` lists each matching ref as `<oid>\t<ref name>`.
+        let output = git_command_output(self, &["ls-remote", remote_name, ref_name])?;
+        for line in output.lines() {
+            if let Some((oid, name)) = line.split_once('\t') {
+                if name == ref_name {
+                    return Ok(Some(Oid::from_str(oid)?));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn fetch_branch(&self, branch_name: &str, remote_name: &str) -> Result<(), GitCommandError> {
+        self.fetch_refspec(
+            &format!(
+                "+refs/heads/{}:refs/remotes/{}/{}",
+                branch_name, remote_name, branch_name
+            ),
+            remote_name,
+        )
     }
 
     fn pull_branch(&self, branch_name: &str, remote_name: &str) -> Result<(), GitCommandError> {
         self.checkout_branch(branch_name)?;
-        execute_git_command(&["pull", remote_name, branch_name], false)
+        execute_git_command(self, &["pull", remote_name, branch_name], false)
+    }
+}
+
+/// The outcome of replaying a range of commits, or merging a commit, in memory.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ReplayOutcome {
+    /// All commits were replayed cleanly, producing the contained head commit.
+    Complete(Oid),
+    /// A commit could not be replayed cleanly.
+    Conflict {
+        /// The commit that could not be replayed or merged.
+        commit: Oid,
+        /// The paths that conflicted.
+        paths: Vec<String>,
+    },
+}
+
+/// A single hunk within a [Diff].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DiffHunkInfo {
+    /// The index of the hunk within the diff.
+    pub index: usize,
+    /// The path of the file the hunk belongs to.
+    pub path: String,
+    /// The hunk header, e.g. `@@ -1,3 +1,4 @@`.
+    pub header: String,
+    /// The line numbers in the old file of the lines removed or modified by the hunk.
+    pub removed_lines: Vec<u32>,
+}
+
+impl Display for DiffHunkInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.path, self.header)
+    }
+}
+
+/// Collects the hunks within a [Diff], in the order that libgit2 walks them. Deltas without any
+/// textual hunks, such as binary files or mode changes, are represented by a single hunk.
+///
+/// ## Takes
+/// - `diff` - The diff to collect the hunks of.
+///
+/// ## Returns
+/// - `Result<Vec<DiffHunkInfo>>` - The hunks within the diff, or an error.
+pub fn diff_hunks(diff: &Diff<'_>) -> Result<Vec<DiffHunkInfo>, git2::Error> {
+    let mut hunks = Vec::new();
+    for delta_index in 0..diff.deltas().len() {
+        let Some(path) = diff.get_delta(delta_index).as_ref().and_then(delta_path) else {
+            continue;
+        };
+        let patch = Patch::from_diff(diff, delta_index)?;
+        let num_hunks = patch.as_ref().map_or(0, |p| p.num_hunks());
+
+        if num_hunks == 0 {
+            hunks.push(DiffHunkInfo {
+                index: hunks.len(),
+                path,
+                header: "(binary or mode change)".to_string(),
+                removed_lines: Vec::new(),
+            });
+            continue;
+        }
+
+        let patch = patch.expect("Patch must exist if it has hunks");
+        for hunk_index in 0..num_hunks {
+            let (hunk, num_lines) = patch.hunk(hunk_index)?;
+            let removed_lines = (0..num_lines)
+                .map(|line_index| patch.line_in_hunk(hunk_index, line_index))
+                .filter_map(|line| match line {
+                    Ok(line) if line.origin() == '-' => line.old_lineno().map(Ok),
+                    Ok(_) => None,
+                    Err(e) => Some(Err(e)),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            hunks.push(DiffHunkInfo {
+                index: hunks.len(),
+                path: path.clone(),
+                header: String::from_utf8_lossy(hunk.header()).trim().to_string(),
+                removed_lines,
+            });
+        }
     }
+    Ok(hunks)
+}
+
+/// Returns the paths of the conflicting entries within an [Index].
+fn conflict_paths(index: &Index) -> Result<Vec<String>, git2::Error> {
+    Ok(index
+        .conflicts()?
+        .filter_map(|c| {
+            let c = c.ok()?;
+            let entry = c.our.or(c.their).or(c.ancestor)?;
+            Some(String::from_utf8_lossy(&entry.path).into_owned())
+        })
+        .collect())
+}
+
+/// Returns the path of the file changed by a [DiffDelta].
+fn delta_path(delta: &DiffDelta<'_>) -> Option<String> {
+    delta
+        .new_file()
+        .path()
+        .or_else(|| delta.old_file().path())
+        .map(|p| p.to_string_lossy().into_owned())
 }
 
 /// Error type for git command execution.
@@ -229,15 +832,20 @@ pub enum GitCommandError {
     Git2(#[from] git2::Error),
 }
 
-/// Executes a `git` command with the given arguments in a blocking child task.
+/// Executes a `git` command with the given arguments in a blocking child task, within the working
+/// tree of the repository (or its git directory, if it is bare).
 ///
 /// ## Takes
+/// - `repository` - The repository to run the command in.
 /// - `args` - The arguments to pass to the `git` command.
 /// - `interactive` - Whether the command should be interactive.
-fn execute_git_command(args: &[&str], interactive: bool) -> Result<(), GitCommandError> {
-    let mut cmd = Command::new("git");
+fn execute_git_command(
+    repository: &Repository,
+    args: &[&str],
+    interactive: bool,
+) -> Result<(), GitCommandError> {
     if interactive {
-        let status = cmd.args(args).status()?;
+        let status = git_command(repository).args(args).status()?;
 
         if !status.success() {
             return Err(GitCommandError::Command(format!(
@@ -246,20 +854,49 @@ fn execute_git_command(args: &[&str], interactive: bool) -> Result<(), GitComman
             )));
         }
     } else {
-        let output = cmd.args(args).output()?;
+        git_command_output(repository, args)?;
+    }
 
-        if !output.status.success() {
-            let git_error = String::from_utf8_lossy(&output.stderr)
-                .trim_end_matches('\n')
-                .replace("\n", &format!("\n{} ", QUOTE_CHAR))
-                .replace("error: ", "");
+    Ok(())
+}
 
-            let error_message = format!("{} Git error:\n{} {}", QUOTE_CHAR, QUOTE_CHAR, git_error);
-            return Err(GitCommandError::Command(
-                Red.paint(error_message).to_string(),
-            ));
-        }
+/// Executes a `git` command with the given arguments in a blocking child task, within the working
+/// tree of the repository (or its git directory, if it is bare), and captures its output.
+///
+/// ## Takes
+/// - `repository` - The repository to run the command in.
+/// - `args` - The arguments to pass to the `git` command.
+///
+/// ## Returns
+/// - `Result<String>` - The standard output of the command.
+fn git_command_output(repository: &Repository, args: &[&str]) -> Result<String, GitCommandError> {
+    let output = git_command(repository).args(args).output()?;
+
+    if !output.status.success() {
+        // Some commands (e.g. `git rebase --continue`) report failures on stdout.
+        let output = if output.stderr.is_empty() {
+            output.stdout
+        } else {
+            output.stderr
+        };
+        let git_error = String::from_utf8_lossy(&output)
+            .trim_end_matches('\n')
+            .replace("\n", &format!("\n{} ", QUOTE_CHAR))
+            .replace("error: ", "");
+
+        let error_message = format!("{} Git error:\n{} {}", QUOTE_CHAR, QUOTE_CHAR, git_error);
+        return Err(GitCommandError::Command(
+            Red.paint(error_message).to_string(),
+        ));
     }
 
-    Ok(())
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Returns a `git` [Command] that runs within the working tree of the repository, or its git
+/// directory if it is bare.
+fn git_command(repository: &Repository) -> Command {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repository.workdir().unwrap_or(repository.path()));
+    cmd
 }
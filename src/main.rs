@@ -1,7 +1,6 @@
 #![doc = include_str!("../README.md")]
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 #![cfg_attr(not(test), warn(unused_crate_dependencies))]
-#![allow(clippy::result_large_err)]
 
 use clap::Parser;
 
@@ -12,6 +11,8 @@ mod ctx;
 mod errors;
 mod git;
 mod subcommands;
+#[cfg(test)]
+mod test_utils;
 mod tree;
 
 #[tokio::main]
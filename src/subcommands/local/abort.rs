@@ -0,0 +1,17 @@
+//! `abort` subcommand.
+
+use crate::{ctx::StContext, errors::StResult};
+use clap::Args;
+
+/// CLI arguments for the `abort` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct AbortCmd;
+
+impl AbortCmd {
+    /// Run the `abort` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        ctx.abort_restack()?;
+        println!("Aborted restack. All branches have been returned to their original state.");
+        Ok(())
+    }
+}
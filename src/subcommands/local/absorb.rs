@@ -0,0 +1,325 @@
+//! `absorb` subcommand.
+
+use crate::{
+    ctx::{RestackPlan, RestackSummary, StContext},
+    errors::{StError, StResult},
+    git::{diff_hunks, DiffHunkInfo, RepositoryExt},
+};
+use clap::Args;
+use git2::{build::CheckoutBuilder, BlameOptions, BranchType, Oid};
+use nu_ansi_term::Color;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+};
+
+/// CLI arguments for the `absorb` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct AbsorbCmd {
+    /// Stage all changes to tracked files before absorbing
+    #[clap(short, long)]
+    all: bool,
+    /// Amend the `HEAD` commit of each target branch, rather than creating fixup commits.
+    #[clap(long)]
+    amend: bool,
+}
+
+impl AbsorbCmd {
+    /// Run the `absorb` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        ctx.check_no_restack_in_progress()?;
+
+        if self.all {
+            ctx.repository.stage_changes(true)?;
+        }
+
+        // Unstaged changes would be lost when the working tree is updated.
+        let unstaged = ctx.repository.diff_index_to_workdir(None, None)?;
+        if unstaged.deltas().len() > 0 {
+            return Err(StError::WorkingTreeDirty);
+        }
+
+        // Collect the staged hunks.
+        let current_branch_name = ctx.repository.current_branch_name()?;
+        let head = ctx.repository.head()?.peel_to_commit()?;
+        let staged = ctx
+            .repository
+            .diff_tree_to_index(Some(&head.tree()?), None, None)?;
+        let hunks = diff_hunks(&staged)?;
+        if hunks.is_empty() {
+            return Err(StError::NothingToCommit);
+        }
+
+        // Map each commit in the stack, up to and including the current branch, to its branch.
+        let stack = ctx.discover_stack()?;
+        let downstack = stack
+            .iter()
+            .skip(1)
+            .take_while(|b| **b != current_branch_name)
+            .chain(std::iter::once(&current_branch_name))
+            .filter(|b| **b != ctx.tree.trunk_name)
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut commit_branches = HashMap::new();
+        for branch in downstack.iter() {
+            for oid in ctx.branch_commits(branch)? {
+                commit_branches.insert(oid, branch.clone());
+            }
+        }
+
+        // Attribute each hunk to the branch that introduced the lines it changes. Hunks that only
+        // add lines have nothing to blame, and are left staged.
+        let mut targets = BTreeMap::<usize, (String, Oid, HashSet<usize>)>::new();
+        let mut ambiguous = Vec::new();
+        let mut additions = Vec::new();
+        for hunk in hunks.iter() {
+            if hunk.removed_lines.is_empty() {
+                additions.push(hunk.to_string());
+                continue;
+            }
+
+            match Self::blame_hunk(ctx, head.id(), hunk, &commit_branches)? {
+                Some((branch, commit)) => {
+                    let position = downstack
+                        .iter()
+                        .position(|b| *b == branch)
+                        .expect("Branch must be in the stack");
+                    targets
+                        .entry(position)
+                        .or_insert_with(|| (branch, commit, HashSet::new()))
+                        .2
+                        .insert(hunk.index);
+                }
+                None => ambiguous.push(hunk.to_string()),
+            }
+        }
+        if !ambiguous.is_empty() {
+            return Err(StError::AmbiguousHunks(ambiguous));
+        }
+        let Some((lowest, _, _)) = targets.values().next() else {
+            return Err(StError::AmbiguousHunks(additions));
+        };
+
+        // Snapshot everything above the lowest target branch before any branches are rewritten.
+        let upstack = ctx.tree.subtree(lowest)?;
+        let mut plan = RestackPlan::new(ctx, &upstack)?;
+        plan.use_cached_upstreams();
+
+        // Build the new tree of each target branch before anything is written. The hunks were
+        // computed against `HEAD`, so they are applied there, and the result is merged onto the
+        // target branch like a cherry-pick, which accounts for lines shifted by the branches
+        // above it.
+        let head_tree = head.tree()?;
+        let mut absorbed = Vec::new();
+        for (branch, commit, hunk_indices) in targets.values() {
+            let branch_head = ctx
+                .repository
+                .find_branch(branch, BranchType::Local)?
+                .get()
+                .peel_to_commit()?;
+            let patched = ctx.repository.find_tree(ctx.repository.apply_hunks(
+                &head_tree,
+                &staged,
+                hunk_indices,
+            )?)?;
+            let mut index =
+                ctx.repository
+                    .merge_trees(&head_tree, &branch_head.tree()?, &patched, None)?;
+            if index.has_conflicts() {
+                return Err(StError::AbsorbConflict(branch.clone()));
+            }
+            let tree = ctx
+                .repository
+                .find_tree(index.write_tree_to(ctx.repository)?)?;
+            absorbed.push((branch, *commit, hunk_indices.len(), branch_head, tree));
+        }
+
+        // Stash the staged changes, so that they are kept until the restack succeeds.
+        let stash = ctx.repository.stash_changes("st: absorb")?;
+
+        // Commit the hunks onto each target branch.
+        let sig = ctx.repository.signature()?;
+        for (branch, commit, num_hunks, branch_head, tree) in absorbed {
+            let new_head = if self.amend {
+                branch_head.amend(None, None, Some(&sig), None, None, Some(&tree))?
+            } else {
+                let target = ctx.repository.find_commit(commit)?;
+                let message = format!("fixup! {}", target.summary().unwrap_or_default());
+                ctx.repository
+                    .commit(None, &sig, &sig, &message, &tree, &[&branch_head])?
+            };
+            ctx.repository.reference(
+                format!("refs/heads/{}", branch).as_str(),
+                new_head,
+                true,
+                "st: absorb",
+            )?;
+
+            println!(
+                "Absorbed {} hunk{} into branch `{}`.",
+                num_hunks,
+                if num_hunks != 1 { "s" } else { "" },
+                Color::Green.paint(branch)
+            );
+        }
+
+        // The staged changes are stashed, so the working tree can be reset to the current branch
+        // before restacking.
+        ctx.repository.force_checkout_branch(&current_branch_name)?;
+
+        let mut summary = RestackSummary::default();
+        let result = ctx.run_restack_plan(plan, &mut summary);
+        println!("\n📚 Restack summary:\n{}", summary);
+        match result {
+            Ok(()) => Self::restore_staged(ctx, stash, &additions),
+            Err(e) => {
+                println!(
+                    "Your staged changes are kept in the stash. Once the restack is complete, restore any that were not absorbed with `{}`.",
+                    Color::Blue.paint("git stash pop")
+                );
+                Err(e)
+            }
+        }
+    }
+
+    /// Restores the staged changes that were not absorbed from the stash, and drops it.
+    ///
+    /// The stashed index is merged onto the restacked current branch, so that only the changes
+    /// which were not absorbed remain staged. If they conflict, the stash is kept.
+    fn restore_staged(ctx: &StContext<'_>, stash: Oid, additions: &[String]) -> StResult<()> {
+        let stash = ctx.repository.find_commit(stash)?;
+        let mut index = ctx.repository.merge_trees(
+            &stash.parent(0)?.tree()?,
+            &ctx.repository.head()?.peel_to_tree()?,
+            &stash.parent(1)?.tree()?,
+            None,
+        )?;
+        if index.has_conflicts() {
+            println!(
+                "Your staged changes could not be restored, and are kept in the stash. Restore them with `{}`.",
+                Color::Blue.paint("git stash pop")
+            );
+            return Ok(());
+        }
+
+        let tree = ctx
+            .repository
+            .find_tree(index.write_tree_to(ctx.repository)?)?;
+        ctx.repository
+            .checkout_tree(tree.as_object(), Some(CheckoutBuilder::new().force()))?;
+        let mut repo_index = ctx.repository.index()?;
+        repo_index.read_tree(&tree)?;
+        repo_index.write()?;
+        ctx.repository.drop_stash()?;
+
+        for hunk in additions {
+            println!(
+                "Left hunk `{}` staged, as it only adds lines.",
+                Color::Yellow.paint(hunk)
+            );
+        }
+        Ok(())
+    }
+
+    /// Blames the lines changed by a hunk, and returns the branch and commit that introduced them.
+    ///
+    /// Returns [None] if the lines were introduced by more than one branch or by commits outside
+    /// of the stack.
+    fn blame_hunk(
+        ctx: &StContext<'_>,
+        head: Oid,
+        hunk: &DiffHunkInfo,
+        commit_branches: &HashMap<Oid, String>,
+    ) -> StResult<Option<(String, Oid)>> {
+        let (Some(&first), Some(&last)) = (hunk.removed_lines.first(), hunk.removed_lines.last())
+        else {
+            return Ok(None);
+        };
+
+        let mut opts = BlameOptions::new();
+        opts.newest_commit(head)
+            .min_line(first as usize)
+            .max_line(last as usize);
+        let blame = ctx
+            .repository
+            .blame_file(Path::new(&hunk.path), Some(&mut opts))?;
+
+        let mut owner: Option<(String, Oid)> = None;
+        for &line in hunk.removed_lines.iter() {
+            let Some(commit) = blame.get_line(line as usize).map(|h| h.final_commit_id()) else {
+                return Ok(None);
+            };
+            let Some(branch) = commit_branches.get(&commit) else {
+                return Ok(None);
+            };
+
+            match owner {
+                Some((ref owner_branch, _)) if owner_branch != branch => return Ok(None),
+                Some(_) => {}
+                None => owner = Some((branch.clone(), commit)),
+            }
+        }
+        Ok(owner)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AbsorbCmd;
+    use crate::test_utils::TestRepo;
+    use git2::Status;
+
+    #[test]
+    fn absorb_into_lower_branch_with_shifted_lines() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "1\n2\n3\n4\n5\n");
+        test_repo.create_branch(&mut ctx, "b", "a.txt", "x\ny\n1\n2\n3\n4\n5\n");
+
+        // Change a line introduced by `a`, which `b` shifted down.
+        test_repo.write("a.txt", "x\ny\n1\n2\n3\nfour\n5\n");
+        test_repo.git(&["add", "a.txt"]);
+        AbsorbCmd {
+            all: false,
+            amend: false,
+        }
+        .run(&mut ctx)
+        .unwrap();
+
+        assert_eq!(test_repo.log("a"), ["fixup! a", "a", "initial commit"]);
+        assert_eq!(test_repo.read("a", "a.txt").unwrap(), "1\n2\n3\nfour\n5\n");
+        assert_eq!(test_repo.log("b"), ["b", "fixup! a", "a", "initial commit"]);
+        assert_eq!(
+            test_repo.read("b", "a.txt").unwrap(),
+            "x\ny\n1\n2\n3\nfour\n5\n"
+        );
+        assert!(test_repo.repo.statuses(None).unwrap().is_empty());
+        assert!(test_repo.repo.find_reference("refs/stash").is_err());
+    }
+
+    #[test]
+    fn absorb_leaves_additions_staged() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "1\n2\n3\n");
+
+        test_repo.write("a.txt", "1\ntwo\n3\n");
+        test_repo.write("new.txt", "new\n");
+        test_repo.git(&["add", "a.txt", "new.txt"]);
+        AbsorbCmd {
+            all: false,
+            amend: true,
+        }
+        .run(&mut ctx)
+        .unwrap();
+
+        assert_eq!(test_repo.log("a"), ["a", "initial commit"]);
+        assert_eq!(test_repo.read("a", "a.txt").unwrap(), "1\ntwo\n3\n");
+        assert_eq!(test_repo.read("a", "new.txt"), None);
+
+        let statuses = test_repo.repo.statuses(None).unwrap();
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses.get(0).unwrap().path(), Some("new.txt"));
+        assert_eq!(statuses.get(0).unwrap().status(), Status::INDEX_NEW);
+    }
+}
@@ -0,0 +1,26 @@
+//! `bottom` subcommand.
+
+use crate::{ctx::StContext, errors::StResult, git::RepositoryExt};
+use clap::Args;
+use nu_ansi_term::Color;
+
+/// CLI arguments for the `bottom` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct BottomCmd;
+
+impl BottomCmd {
+    /// Run the `bottom` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        let current_branch = ctx.repository.current_branch_name()?;
+        match ctx.resolve_bottom(&current_branch)? {
+            Some(target) => ctx.checkout_tracked_branch(&target),
+            None => {
+                println!(
+                    "No stacks are tracked on top of `{}`.",
+                    Color::Blue.paint(&ctx.tree.trunk_name)
+                );
+                Ok(())
+            }
+        }
+    }
+}
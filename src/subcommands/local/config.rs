@@ -1,13 +1,32 @@
 //! `config` subcommand.
 
-use crate::{config::prompt_for_configuration, ctx::StContext, errors::StResult};
+use crate::{
+    config::{prompt_for_configuration, prompt_for_repo_configuration, RepoConfig},
+    ctx::StContext,
+    errors::StResult,
+};
 
 #[derive(Debug, Clone, Eq, PartialEq, clap::Args)]
-pub struct ConfigCmd;
+pub struct ConfigCmd {
+    /// Edit the configuration for the current repository, rather than the global configuration.
+    #[clap(long)]
+    repo: bool,
+}
 
 impl ConfigCmd {
     /// Run the `config` subcommand to force or allow configuration editing.
-    pub fn run(self, mut ctx: StContext<'_>) -> StResult<()> {
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        if self.repo {
+            let existing = Some(RepoConfig::path(ctx.repository))
+                .filter(|p| p.exists())
+                .map(std::fs::read_to_string)
+                .transpose()?;
+            let repo_cfg = prompt_for_repo_configuration(existing.as_deref())?;
+            repo_cfg.save(ctx.repository)?;
+            ctx.repo_cfg = repo_cfg;
+            return Ok(());
+        }
+
         let ser = toml::to_string_pretty(&ctx.cfg)?;
         let cfg = prompt_for_configuration(Some(&ser))?;
         ctx.cfg = cfg;
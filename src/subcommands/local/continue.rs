@@ -0,0 +1,24 @@
+//! `continue` subcommand.
+
+use crate::{
+    ctx::{RestackSummary, StContext},
+    errors::StResult,
+};
+use clap::Args;
+
+/// CLI arguments for the `continue` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct ContinueCmd;
+
+impl ContinueCmd {
+    /// Run the `continue` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        let mut summary = RestackSummary::default();
+        let result = ctx.continue_restack(&mut summary);
+
+        if !summary.is_empty() {
+            println!("\n📚 Restack summary:\n{}", summary);
+        }
+        result
+    }
+}
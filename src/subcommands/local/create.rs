@@ -1,12 +1,11 @@
 //! `create` subcommand.
 
 use crate::{
-    ctx::StContext,
+    ctx::{RestackSummary, StContext},
     errors::{StError, StResult},
     git::RepositoryExt,
 };
 use clap::Args;
-use git2::IndexAddOption;
 use nu_ansi_term::Color;
 
 /// CLI arguments for the `create` subcommand.
@@ -24,6 +23,9 @@ pub struct CreateCmd {
     /// Specify a commit message
     #[clap(short, long, requires = "all", conflicts_with = "update")]
     message: Option<String>,
+    /// Insert the new branch between the current branch and its children
+    #[clap(short, long)]
+    insert: bool,
     /// The remote to push to (defaults to "origin")
     #[clap(short, long = "remote")]
     remote: Option<String>,
@@ -31,7 +33,7 @@ pub struct CreateCmd {
 
 impl CreateCmd {
     /// Run the `create` subcommand.
-    pub fn run(self, mut ctx: StContext<'_>) -> StResult<()> {
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
         // Override the remote name if provided.
         ctx.set_remote_name(self.remote.clone());
 
@@ -46,36 +48,38 @@ impl CreateCmd {
             None => inquire::Text::new("Name of new branch:").prompt()?,
         };
 
+        // Prompt the user for the children to adopt, if inserting the new branch.
+        let adopted_children = if self.insert {
+            ctx.check_no_restack_in_progress()?;
+            Self::prompt_adopted_children(ctx, &current_branch_name)?
+        } else {
+            Vec::new()
+        };
+
         // Stage changes if requested
         if self.all || self.update {
             let message = self.message.ok_or(StError::CommitMessageRequired)?;
 
-            // Get the index.
-            let mut index = ctx.repository.index()?;
-
             // Stage changes based on flag.
-            if self.all {
-                index.add_all(vec!["*"], IndexAddOption::DEFAULT, None)?;
-            } else if self.update {
-                index.update_all(vec!["*"], None)?;
-            }
-            index.write()?;
+            ctx.repository.stage_changes(!self.all)?;
 
             // Create the commit.
             let sig = ctx.repository.signature()?;
-            // Get the tree.
             let tree_id = ctx.repository.index()?.write_tree()?;
             let tree = ctx.repository.find_tree(tree_id)?;
-            // Get the parent commit.
-            let parent_commit = ctx.repository.head()?.peel_to_commit()?;
-            // Create the commit.
-            ctx.repository
-                .commit(Some("HEAD"), &sig, &sig, &message, &tree, &[&parent_commit])?;
+            ctx.repository.commit(
+                Some("HEAD"),
+                &sig,
+                &sig,
+                &message,
+                &tree,
+                &[&current_branch_head],
+            )?;
         }
 
         // Check if the working tree is clean.
         if !ctx.repository.is_working_tree_clean()? {
-            return Err(crate::errors::StError::WorkingTreeDirty);
+            return Err(StError::WorkingTreeDirty);
         }
 
         // Attempt to create the new branch.
@@ -95,6 +99,90 @@ impl CreateCmd {
             Color::Blue.paint(&new_branch_name),
             Color::Blue.paint(&current_branch_name)
         );
-        Ok(())
+
+        if adopted_children.is_empty() {
+            return Ok(());
+        }
+
+        // Move the adopted children on top of the new branch, and restack them onto it.
+        for child in adopted_children.iter() {
+            ctx.tree.reparent(child, &new_branch_name)?;
+        }
+        let branches = ctx.tree.subtree(&new_branch_name)?;
+        let mut summary = RestackSummary::default();
+        let result = ctx.restack_branches(&branches, &mut summary);
+        println!("\n📚 Restack summary:\n{}", summary);
+        result
+    }
+
+    /// Prompts the user for the children of the current branch that the new branch should adopt.
+    /// If the current branch has a single child, it is adopted without prompting.
+    fn prompt_adopted_children(ctx: &StContext<'_>, branch_name: &str) -> StResult<Vec<String>> {
+        let mut children = ctx
+            .tree
+            .get(branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(branch_name.to_string()))?
+            .children
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        children.sort();
+
+        if children.len() <= 1 {
+            return Ok(children);
+        }
+
+        let prompt = format!(
+            "Select the children of `{}` to move on top of the new branch",
+            Color::Blue.paint(branch_name)
+        );
+        Ok(inquire::MultiSelect::new(prompt.as_str(), children)
+            .with_all_selected_by_default()
+            .prompt()?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CreateCmd;
+    use crate::{errors::StError, git::RepositoryExt, test_utils::TestRepo};
+
+    fn create_cmd(branch_name: &str, message: Option<&str>) -> CreateCmd {
+        CreateCmd {
+            branch_name: Some(branch_name.to_string()),
+            all: message.is_some(),
+            update: false,
+            message: message.map(String::from),
+            insert: false,
+            remote: None,
+        }
+    }
+
+    #[test]
+    fn create_all_commits_onto_current_branch() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+
+        test_repo.write("a.txt", "changed\n");
+        create_cmd("b", Some("change a")).run(&mut ctx).unwrap();
+
+        assert_eq!(test_repo.repo.current_branch_name().unwrap(), "b");
+        assert_eq!(test_repo.log("a"), ["change a", "a", "initial commit"]);
+        assert_eq!(test_repo.log("b"), ["a", "initial commit"]);
+        assert_eq!(ctx.tree.get("b").unwrap().parent.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn create_refuses_dirty_working_tree() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+
+        test_repo.write("README.md", "changed\n");
+        assert!(matches!(
+            create_cmd("a", None).run(&mut ctx),
+            Err(StError::WorkingTreeDirty)
+        ));
+        assert!(ctx.tree.get("a").is_none());
     }
 }
@@ -14,7 +14,7 @@ pub struct DeleteCmd {
 
 impl DeleteCmd {
     /// Run the `delete` subcommand.
-    pub fn run(self, mut ctx: StContext<'_>) -> StResult<()> {
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
         // Gather the display branches.
         let display_branches = ctx.display_branches()?;
 
@@ -0,0 +1,131 @@
+//! `doctor` subcommand.
+
+use crate::{
+    ctx::{StContext, StackProblem},
+    errors::StResult,
+};
+use clap::Args;
+use nu_ansi_term::Color;
+use octocrab::Octocrab;
+
+/// CLI arguments for the `doctor` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct DoctorCmd {
+    /// Apply every fix without asking for confirmation.
+    #[clap(long, short)]
+    yes: bool,
+}
+
+impl DoctorCmd {
+    /// Run the `doctor` subcommand.
+    pub async fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        ctx.check_no_restack_in_progress()?;
+
+        // Fixing one problem may resolve or reveal others, so the tree is diagnosed again after
+        // each fix. Problems that were declined or could not be fixed are skipped on later
+        // passes.
+        let mut num_fixed = 0;
+        let mut unfixed = Vec::new();
+        loop {
+            let problems = ctx.diagnose()?;
+            let Some(problem) = problems.into_iter().find(|p| !unfixed.contains(p)) else {
+                break;
+            };
+            if self.resolve(ctx, &problem)? {
+                num_fixed += 1;
+            } else {
+                unfixed.push(problem);
+            }
+        }
+
+        for problem in self.pull_request_problems(ctx).await? {
+            if self.resolve(ctx, &problem)? {
+                num_fixed += 1;
+            } else {
+                unfixed.push(problem);
+            }
+        }
+
+        if num_fixed == 0 && unfixed.is_empty() {
+            println!("🩺 No problems found.");
+        } else {
+            println!(
+                "\n🩺 Fixed {} problem{}, {} left unfixed.",
+                num_fixed,
+                if num_fixed != 1 { "s" } else { "" },
+                unfixed.len()
+            );
+        }
+        Ok(())
+    }
+
+    /// Explains a problem, and fixes it if the user agrees.
+    ///
+    /// ## Returns
+    /// - `Result<bool>` - Whether the problem was fixed.
+    fn resolve(&self, ctx: &mut StContext<'_>, problem: &StackProblem) -> StResult<bool> {
+        println!("⚠️ {}", problem);
+        let fix = self.yes
+            || inquire::Confirm::new(&problem.fix_description(&ctx.tree.trunk_name))
+                .with_default(true)
+                .prompt()?;
+        if !fix {
+            return Ok(false);
+        }
+
+        let fixed = ctx.fix_problem(problem)?;
+        if !fixed {
+            println!("The problem cannot be fixed automatically, as the branch shares no history with its parent.");
+        }
+        Ok(fixed)
+    }
+
+    /// Checks that the pull request linked to each branch exists, and that its head is the
+    /// branch.
+    async fn pull_request_problems(&self, ctx: &StContext<'_>) -> StResult<Vec<StackProblem>> {
+        let mut linked = ctx
+            .tree
+            .branches
+            .values()
+            .filter_map(|b| b.remote.as_ref().map(|r| (b.name.clone(), r.pr_number)))
+            .collect::<Vec<_>>();
+        if linked.is_empty() {
+            return Ok(Vec::new());
+        }
+        linked.sort();
+
+        // Establish the GitHub API client.
+        let gh_client = Octocrab::builder()
+            .personal_token(ctx.cfg.github_token.clone())
+            .build()?;
+        let (owner, repo) = ctx.owner_and_repository()?;
+        let pulls = gh_client.pulls(&owner, &repo);
+
+        println!(
+            "🐙 Checking {} linked pull request{} on `{}`...",
+            linked.len(),
+            if linked.len() != 1 { "s" } else { "" },
+            Color::Blue.paint(format!("{}/{}", owner, repo))
+        );
+        let mut problems = Vec::new();
+        for (branch, pr_number) in linked {
+            let head = match pulls.get(pr_number).await {
+                Ok(pr) if pr.head.ref_field == branch => continue,
+                Ok(pr) => Some(pr.head.ref_field),
+                Err(octocrab::Error::GitHub { source, .. })
+                    if source.status_code.as_u16() == 404 =>
+                {
+                    None
+                }
+                Err(e) => return Err(e.into()),
+            };
+            problems.push(StackProblem::PullRequestMismatch {
+                branch,
+                pr_number,
+                head,
+            });
+        }
+
+        Ok(problems)
+    }
+}
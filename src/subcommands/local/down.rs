@@ -0,0 +1,21 @@
+//! `down` subcommand.
+
+use crate::{ctx::StContext, errors::StResult, git::RepositoryExt};
+use clap::Args;
+
+/// CLI arguments for the `down` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct DownCmd {
+    /// Number of branches to move downstack.
+    #[clap(index = 1, default_value_t = 1)]
+    steps: usize,
+}
+
+impl DownCmd {
+    /// Run the `down` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        let current_branch = ctx.repository.current_branch_name()?;
+        let target = ctx.resolve_downstack(&current_branch, self.steps)?;
+        ctx.checkout_tracked_branch(&target)
+    }
+}
@@ -0,0 +1,125 @@
+//! `fold` subcommand.
+
+use crate::{
+    ctx::{RestackPlan, RestackSummary, StContext},
+    errors::{StError, StResult},
+    git::RepositoryExt,
+};
+use clap::Args;
+use git2::BranchType;
+use nu_ansi_term::Color;
+
+/// CLI arguments for the `fold` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct FoldCmd;
+
+impl FoldCmd {
+    /// Run the `fold` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        ctx.check_no_restack_in_progress()?;
+
+        // Gather metadata about the current branch.
+        let current_branch_name = ctx.repository.current_branch_name()?;
+        let tracked_branch = ctx
+            .tree
+            .get(&current_branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(current_branch_name.clone()))?
+            .clone();
+        let parent_name = tracked_branch
+            .parent
+            .ok_or(StError::CannotModifyTrunkBranch)?;
+        if parent_name == ctx.tree.trunk_name {
+            return Err(StError::CannotFoldIntoTrunk(current_branch_name));
+        }
+        if tracked_branch.parent_oid_cache.is_none() {
+            return Err(StError::MissingParentOidCache);
+        }
+
+        if !ctx.repository.is_working_tree_clean()? {
+            return Err(StError::WorkingTreeDirty);
+        }
+
+        // Ensure the branch is restacked onto its parent, so that the parent can be fast-forwarded.
+        ctx.branch_commits(&current_branch_name)?;
+        let head = ctx.repository.current_branch()?.get().peel_to_commit()?;
+
+        // Fast-forward the parent to the branch's `HEAD` commit, and check it out. The trees are
+        // identical, so the working tree does not need to be updated.
+        ctx.repository.reference(
+            format!("refs/heads/{}", parent_name).as_str(),
+            head.id(),
+            true,
+            format!("st: fold {}", current_branch_name).as_str(),
+        )?;
+        ctx.repository
+            .set_head(format!("refs/heads/{}", parent_name).as_str())?;
+
+        // Delete the branch, re-linking its children to the parent.
+        ctx.repository
+            .find_branch(&current_branch_name, BranchType::Local)?
+            .delete()?;
+        ctx.tree.delete(&current_branch_name)?;
+
+        println!(
+            "Folded branch `{}` into `{}`.",
+            Color::Green.paint(&current_branch_name),
+            Color::Yellow.paint(&parent_name)
+        );
+        if let Some(remote) = tracked_branch.remote.as_ref() {
+            println!(
+                "Pull request #{} for `{}` is no longer tracked, and can be closed.",
+                remote.pr_number,
+                Color::Green.paint(&current_branch_name)
+            );
+        }
+
+        // Restack the parent's other children onto its new `HEAD` commit.
+        let descendants = ctx.tree.subtree(&parent_name)?[1..].to_vec();
+        let mut plan = RestackPlan::new(ctx, &descendants)?;
+        plan.use_cached_upstreams();
+
+        let mut summary = RestackSummary::default();
+        let result = ctx.run_restack_plan(plan, &mut summary);
+        if !summary.is_empty() {
+            println!("\n📚 Restack summary:\n{}", summary);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FoldCmd;
+    use crate::{errors::StError, git::RepositoryExt, test_utils::TestRepo};
+    use git2::BranchType;
+
+    #[test]
+    fn fold_fast_forwards_parent() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        let b_head = test_repo.create_branch(&mut ctx, "b", "b.txt", "b\n");
+        test_repo.create_branch(&mut ctx, "c", "c.txt", "c\n");
+        test_repo.checkout("b");
+
+        FoldCmd.run(&mut ctx).unwrap();
+
+        assert_eq!(test_repo.head("a"), b_head);
+        assert_eq!(test_repo.repo.current_branch_name().unwrap(), "a");
+        assert!(test_repo.repo.find_branch("b", BranchType::Local).is_err());
+        assert_eq!(ctx.tree.get("c").unwrap().parent.as_deref(), Some("a"));
+        assert_eq!(test_repo.log("c"), ["c", "b", "a", "initial commit"]);
+    }
+
+    #[test]
+    fn fold_refuses_trunk_parent() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+
+        assert!(matches!(
+            FoldCmd.run(&mut ctx),
+            Err(StError::CannotFoldIntoTrunk(branch)) if branch == "a"
+        ));
+    }
+}
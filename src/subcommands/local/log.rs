@@ -9,7 +9,7 @@ pub struct LogCmd;
 
 impl LogCmd {
     /// Run the `log` subcommand.
-    pub fn run(self, ctx: StContext<'_>) -> StResult<()> {
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
         ctx.print_tree()?;
         Ok(())
     }
@@ -6,6 +6,9 @@ pub use log::LogCmd;
 mod create;
 pub use create::CreateCmd;
 
+mod modify;
+pub use modify::ModifyCmd;
+
 mod delete;
 pub use delete::DeleteCmd;
 
@@ -15,6 +18,48 @@ pub use checkout::CheckoutCmd;
 mod restack;
 pub use restack::RestackCmd;
 
+mod split;
+pub use split::SplitCmd;
+
+mod squash;
+pub use squash::SquashCmd;
+
+mod fold;
+pub use fold::FoldCmd;
+
+mod absorb;
+pub use absorb::AbsorbCmd;
+
+mod rename;
+pub use rename::RenameCmd;
+
+mod reorder;
+pub use reorder::ReorderCmd;
+
+mod r#move;
+pub use r#move::MoveCmd;
+
+mod r#continue;
+pub use r#continue::ContinueCmd;
+
+mod abort;
+pub use abort::AbortCmd;
+
+mod repair;
+pub use repair::RepairCmd;
+
+mod doctor;
+pub use doctor::DoctorCmd;
+
+mod undo;
+pub use undo::UndoCmd;
+
+mod redo;
+pub use redo::RedoCmd;
+
+mod snapshot;
+pub use snapshot::SnapshotCmd;
+
 mod track;
 pub use track::TrackCmd;
 
@@ -23,3 +68,18 @@ pub use untrack::UntrackCmd;
 
 mod config;
 pub use config::ConfigCmd;
+
+mod up;
+pub use up::UpCmd;
+
+mod down;
+pub use down::DownCmd;
+
+mod top;
+pub use top::TopCmd;
+
+mod bottom;
+pub use bottom::BottomCmd;
+
+mod trunk;
+pub use trunk::TrunkCmd;
@@ -0,0 +1,128 @@
+//! `modify` subcommand.
+
+use crate::{
+    ctx::{RestackSummary, StContext},
+    errors::{StError, StResult},
+    git::RepositoryExt,
+};
+use clap::Args;
+use nu_ansi_term::Color;
+
+/// CLI arguments for the `modify` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct ModifyCmd {
+    /// Stage all changes before modifying the branch
+    #[clap(short = 'a', long = "all")]
+    all: bool,
+    /// Stage only tracked files before modifying the branch
+    #[clap(short, long = "update", conflicts_with = "all")]
+    update: bool,
+    /// Create a new commit on the branch rather than amending its `HEAD` commit
+    #[clap(short, long, requires = "message")]
+    commit: bool,
+    /// Specify a commit message. When amending, defaults to the existing message.
+    #[clap(short, long)]
+    message: Option<String>,
+}
+
+impl ModifyCmd {
+    /// Run the `modify` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        // Gather metadata about the current branch.
+        let current_branch_name = ctx.repository.current_branch_name()?;
+        if ctx.tree.get(&current_branch_name).is_none() {
+            return Err(StError::BranchNotTracked(current_branch_name));
+        } else if current_branch_name == ctx.tree.trunk_name {
+            return Err(StError::CannotModifyTrunkBranch);
+        }
+
+        // Stage changes if requested.
+        if self.all || self.update {
+            ctx.repository.stage_changes(self.update)?;
+        }
+
+        // Write the index to a tree.
+        let tree_id = ctx.repository.index()?.write_tree()?;
+        let tree = ctx.repository.find_tree(tree_id)?;
+        let head_commit = ctx.repository.head()?.peel_to_commit()?;
+
+        if self.commit {
+            // Refuse to create an empty commit.
+            if head_commit.tree_id() == tree_id {
+                return Err(StError::NothingToCommit);
+            }
+
+            let message = self.message.ok_or(StError::CommitMessageRequired)?;
+            let sig = ctx.repository.signature()?;
+            ctx.repository
+                .commit(Some("HEAD"), &sig, &sig, &message, &tree, &[&head_commit])?;
+        } else {
+            head_commit.amend(
+                Some("HEAD"),
+                None,
+                None,
+                None,
+                self.message.as_deref(),
+                Some(&tree),
+            )?;
+        }
+
+        println!(
+            "{} branch `{}`.",
+            if self.commit {
+                "Committed to"
+            } else {
+                "Amended"
+            },
+            Color::Green.paint(&current_branch_name)
+        );
+
+        // Restack all branches above the modified branch. The branches are replayed in memory,
+        // so unstaged changes in the working tree are left alone.
+        let mut summary = RestackSummary::default();
+        let result = ctx.restack_upstack(&current_branch_name, &mut summary);
+        println!("\n📚 Restack summary:\n{}", summary);
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ModifyCmd;
+    use crate::test_utils::TestRepo;
+    use std::path::Path;
+
+    #[test]
+    fn modify_keeps_unstaged_changes_and_restacks() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        test_repo.create_branch(&mut ctx, "b", "b.txt", "b\n");
+        test_repo.checkout("a");
+
+        // Stage one change, and leave another change and an untracked file unstaged.
+        test_repo.write("a.txt", "amended\n");
+        let mut index = test_repo.repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+        test_repo.write("README.md", "unstaged\n");
+        test_repo.write("untracked.txt", "untracked\n");
+        ModifyCmd {
+            all: false,
+            update: false,
+            commit: false,
+            message: None,
+        }
+        .run(&mut ctx)
+        .unwrap();
+
+        assert_eq!(test_repo.read("a", "a.txt").unwrap(), "amended\n");
+        assert_eq!(test_repo.read("b", "a.txt").unwrap(), "amended\n");
+        assert_eq!(test_repo.log("b"), ["b", "a", "initial commit"]);
+        assert!(!ctx.needs_restack("b").unwrap());
+        assert_eq!(
+            std::fs::read_to_string(test_repo.dir.path().join("README.md")).unwrap(),
+            "unstaged\n"
+        );
+    }
+}
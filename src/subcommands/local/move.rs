@@ -0,0 +1,148 @@
+//! `move` subcommand.
+
+use crate::{
+    ctx::{RestackPlan, RestackSummary, StContext},
+    errors::{StError, StResult},
+    git::RepositoryExt,
+};
+use clap::Args;
+use nu_ansi_term::Color;
+
+/// CLI arguments for the `move` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct MoveCmd {
+    /// Name of the tracked branch to move the current branch onto.
+    #[clap(long)]
+    onto: Option<String>,
+}
+
+impl MoveCmd {
+    /// Run the `move` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        ctx.check_no_restack_in_progress()?;
+
+        // Gather metadata about the current branch.
+        let current_branch_name = ctx.repository.current_branch_name()?;
+        let tracked_branch = ctx
+            .tree
+            .get(&current_branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(current_branch_name.clone()))?
+            .clone();
+
+        // Prompt the user for the new parent of the current branch, or use the provided name.
+        let onto = match self.onto {
+            Some(name) => name,
+            None => {
+                let prompt = format!(
+                    "Select the new parent of `{}`",
+                    Color::Blue.paint(&current_branch_name)
+                );
+                inquire::Select::new(prompt.as_str(), ctx.display_branches()?)
+                    .with_formatter(&|f| f.value.branch_name.clone())
+                    .prompt()?
+                    .branch_name
+            }
+        };
+
+        if tracked_branch.parent.as_deref() == Some(onto.as_str()) {
+            println!(
+                "Branch `{}` is already on top of `{}`.",
+                Color::Green.paint(&current_branch_name),
+                Color::Yellow.paint(&onto)
+            );
+            return Ok(());
+        }
+
+        // The branch's own commits begin after the cached parent oid.
+        if tracked_branch.parent_oid_cache.is_none() {
+            return Err(StError::MissingParentOidCache);
+        }
+
+        if !ctx.repository.is_working_tree_clean()? {
+            return Err(StError::WorkingTreeDirty);
+        }
+
+        // Snapshot the subtree prior to moving it, so that the move can be aborted.
+        let branches = ctx.tree.subtree(&current_branch_name)?;
+        let mut plan = RestackPlan::new(ctx, &branches)?;
+
+        // Move the branch within the stack tree, and restack it and its descendants.
+        ctx.tree.reparent(&current_branch_name, &onto)?;
+        plan.retarget(&current_branch_name, &onto);
+        plan.use_cached_upstreams();
+
+        let mut summary = RestackSummary::default();
+        let result = ctx.run_restack_plan(plan, &mut summary);
+        println!("\n📚 Restack summary:\n{}", summary);
+        result?;
+
+        println!(
+            "Moved branch `{}` on top of `{}`.",
+            Color::Green.paint(&current_branch_name),
+            Color::Yellow.paint(&onto)
+        );
+        if let Some(remote) = tracked_branch.remote.as_ref() {
+            println!(
+                "The base of pull request #{} will be updated to `{}` on the next `{}`.",
+                remote.pr_number,
+                Color::Yellow.paint(&onto),
+                Color::Blue.paint("st submit")
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MoveCmd;
+    use crate::test_utils::TestRepo;
+
+    #[test]
+    fn move_onto_trunk_drops_parent_commits() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        test_repo.create_branch(&mut ctx, "b", "b.txt", "b\n");
+
+        MoveCmd {
+            onto: Some("main".to_string()),
+        }
+        .run(&mut ctx)
+        .unwrap();
+
+        assert_eq!(test_repo.log("b"), ["b", "initial commit"]);
+        assert_eq!(test_repo.log("a"), ["a", "initial commit"]);
+        assert_eq!(ctx.tree.get("b").unwrap().parent.as_deref(), Some("main"));
+        assert!(ctx.tree.get("main").unwrap().children.contains("b"));
+        assert!(!ctx.tree.get("a").unwrap().children.contains("b"));
+        assert_eq!(
+            ctx.tree.get("b").unwrap().parent_oid_cache,
+            Some(test_repo.head("main").to_string())
+        );
+    }
+
+    #[test]
+    fn move_carries_subtree() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "c", "c.txt", "c\n");
+        test_repo.checkout("main");
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        test_repo.create_branch(&mut ctx, "b", "b.txt", "b\n");
+        test_repo.checkout("a");
+
+        MoveCmd {
+            onto: Some("c".to_string()),
+        }
+        .run(&mut ctx)
+        .unwrap();
+
+        assert_eq!(test_repo.log("a"), ["a", "c", "initial commit"]);
+        assert_eq!(test_repo.log("b"), ["b", "a", "c", "initial commit"]);
+        assert_eq!(ctx.tree.get("a").unwrap().parent.as_deref(), Some("c"));
+        assert_eq!(ctx.tree.get("b").unwrap().parent.as_deref(), Some("a"));
+        assert!(!ctx.needs_restack("b").unwrap());
+        assert_eq!(test_repo.repo.head().unwrap().shorthand(), Some("a"));
+    }
+}
@@ -0,0 +1,26 @@
+//! `redo` subcommand.
+
+use crate::{
+    ctx::{JournalOutcome, StContext},
+    errors::StResult,
+};
+use clap::Args;
+
+/// CLI arguments for the `redo` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct RedoCmd;
+
+impl RedoCmd {
+    /// Run the `redo` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        ctx.disable_journal();
+        ctx.check_no_restack_in_progress()?;
+
+        match ctx.redo_operation()? {
+            JournalOutcome::Applied(entry) => println!("Redid {}", entry),
+            JournalOutcome::Empty => println!("Nothing to redo."),
+            JournalOutcome::Aborted => println!("Redo aborted. No branches were changed."),
+        }
+        Ok(())
+    }
+}
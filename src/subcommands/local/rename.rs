@@ -0,0 +1,190 @@
+//! `rename` subcommand.
+
+use crate::{
+    ctx::StContext,
+    errors::{StError, StResult},
+    git::RepositoryExt,
+    tree::RemoteMetadata,
+};
+use clap::Args;
+use git2::BranchType;
+use nu_ansi_term::Color;
+use octocrab::{params, Octocrab};
+
+/// CLI arguments for the `rename` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct RenameCmd {
+    /// The new name of the current branch.
+    #[clap(index = 1)]
+    new_name: String,
+    /// Push the renamed branch, and replace its pull request with one for the new branch.
+    #[clap(long, short)]
+    push: bool,
+}
+
+impl RenameCmd {
+    /// Run the `rename` subcommand.
+    pub async fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        ctx.check_no_restack_in_progress()?;
+
+        // Gather metadata about the current branch.
+        let current_branch_name = ctx.repository.current_branch_name()?;
+        if current_branch_name == ctx.tree.trunk_name {
+            return Err(StError::CannotModifyTrunkBranch);
+        }
+        let tracked_branch = ctx
+            .tree
+            .get(&current_branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(current_branch_name.clone()))?
+            .clone();
+
+        if ctx.tree.get(&self.new_name).is_some() {
+            return Err(StError::BranchAlreadyTracked(self.new_name));
+        }
+
+        // GitHub does not allow changing the head branch of a pull request. Unless the pull
+        // request is being replaced, warn the user before the branch loses its link to the remote.
+        if let (Some(remote), false) = (tracked_branch.remote.as_ref(), self.push) {
+            let confirm = inquire::Confirm::new(
+                format!(
+                    "Pull request #{} will keep tracking the remote branch `{}`, and will no longer be updated by `{}`. Pass `{}` to replace it instead. Continue?",
+                    remote.pr_number,
+                    Color::Green.paint(&current_branch_name),
+                    Color::Blue.paint("st submit"),
+                    Color::Blue.paint("--push")
+                )
+                .as_str(),
+            )
+            .with_default(false)
+            .prompt()?;
+
+            if !confirm {
+                return Ok(());
+            }
+        }
+
+        // Rename the git branch, and the branch within the stack tree.
+        ctx.repository
+            .find_branch(&current_branch_name, BranchType::Local)?
+            .rename(&self.new_name, false)?;
+        ctx.tree.rename(&current_branch_name, &self.new_name)?;
+
+        println!(
+            "Renamed branch `{}` to `{}`.",
+            Color::Green.paint(&current_branch_name),
+            Color::Green.paint(&self.new_name)
+        );
+
+        if let (Some(remote), true) = (tracked_branch.remote.as_ref(), self.push) {
+            // Save the rename before replacing the pull request, so that it is kept if any of
+            // the GitHub API calls fail.
+            ctx.save()?;
+
+            let parent = tracked_branch
+                .parent
+                .as_deref()
+                .ok_or(StError::CannotModifyTrunkBranch)?;
+            self.replace_pull_request(ctx, &current_branch_name, parent, remote)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Pushes the renamed branch, opens a pull request for it that replaces the pull request of
+    /// the old branch, and closes the old pull request.
+    async fn replace_pull_request(
+        &self,
+        ctx: &mut StContext<'_>,
+        old_name: &str,
+        parent: &str,
+        remote: &RemoteMetadata,
+    ) -> StResult<()> {
+        let remote_name = remote
+            .name
+            .clone()
+            .or(ctx.remote_name.clone())
+            .unwrap_or("origin".to_string());
+
+        // Establish the GitHub API client.
+        let gh_client = Octocrab::builder()
+            .personal_token(ctx.cfg.github_token.clone())
+            .build()?;
+        let (owner, repo) = ctx.owner_and_repository()?;
+        let pulls = gh_client.pulls(&owner, &repo);
+
+        // Push the renamed branch, and open a pull request that mirrors the old one.
+        let old_pr = pulls.get(remote.pr_number).await?;
+        ctx.repository
+            .push_branch(&self.new_name, &remote_name, false)?;
+        let new_pr = pulls
+            .create(
+                old_pr.title.clone().unwrap_or_default(),
+                &self.new_name,
+                parent,
+            )
+            .body(old_pr.body.clone().unwrap_or_default())
+            .draft(old_pr.draft.unwrap_or_default())
+            .send()
+            .await?;
+
+        // Retarget the open pull requests based on the old branch, as GitHub closes them once
+        // their base branch is deleted.
+        let page = pulls
+            .list()
+            .state(params::State::Open)
+            .base(old_name)
+            .per_page(100)
+            .send()
+            .await?;
+        for child_pr in gh_client.all_pages(page).await? {
+            pulls
+                .update(child_pr.number)
+                .base(&self.new_name)
+                .send()
+                .await?;
+            println!(
+                "Updated base branch for pull request #{} to `{}`.",
+                child_pr.number,
+                Color::Yellow.paint(&self.new_name)
+            );
+        }
+
+        // Point the old pull request at its replacement, close it, and remove the old branch
+        // from the remote.
+        gh_client
+            .issues(&owner, &repo)
+            .create_comment(
+                remote.pr_number,
+                format!(
+                    "Superseded by #{} after renaming the branch to `{}`.",
+                    new_pr.number, self.new_name
+                ),
+            )
+            .await?;
+        pulls
+            .update(remote.pr_number)
+            .state(params::pulls::State::Closed)
+            .send()
+            .await?;
+        ctx.repository
+            .delete_remote_branch(old_name, &remote_name)?;
+
+        // Link the branch to its new pull request.
+        ctx.tree
+            .get_mut(&self.new_name)
+            .ok_or_else(|| StError::BranchNotTracked(self.new_name.clone()))?
+            .remote = Some(RemoteMetadata::new(remote.name.clone(), new_pr.number));
+
+        let pr_link = format!(
+            "https://github.com/{}/{}/pull/{}",
+            owner, repo, new_pr.number
+        );
+        println!(
+            "Replaced pull request #{} with a new pull request for branch `{}` @ `{}`",
+            remote.pr_number,
+            Color::Green.paint(&self.new_name),
+            Color::Blue.paint(pr_link)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+//! `reorder` subcommand.
+
+use crate::{
+    ctx::{RestackPlan, RestackSummary, StContext},
+    errors::{StError, StResult},
+    git::RepositoryExt,
+};
+use clap::Args;
+use nu_ansi_term::Color;
+use std::collections::HashSet;
+
+/// Instructions appended to the branch list when editing the order of the stack.
+const REORDER_INSTRUCTIONS: &str = "
+# Reorder the branches of the stack by reordering the lines above.
+# The first branch is placed on top of the trunk branch, and each following
+# branch on top of the one before it. Lines starting with '#' are ignored.";
+
+/// CLI arguments for the `reorder` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct ReorderCmd;
+
+impl ReorderCmd {
+    /// Run the `reorder` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        ctx.check_no_restack_in_progress()?;
+
+        // Discover the current stack, excluding the trunk branch.
+        let stack = ctx.discover_stack()?;
+        let (base, branches) = stack.split_first().ok_or(StError::BranchUnavailable)?;
+        if branches.len() < 2 {
+            println!("The current stack has fewer than two branches. Nothing to reorder.");
+            return Ok(());
+        }
+
+        // Only linear stacks can be reordered; every branch must have at most one child, which
+        // is the next branch in the stack.
+        for (i, branch_name) in branches.iter().enumerate() {
+            let children = &ctx
+                .tree
+                .get(branch_name)
+                .ok_or_else(|| StError::BranchNotTracked(branch_name.clone()))?
+                .children;
+            let is_linear = match branches.get(i + 1) {
+                Some(next) => children.len() == 1 && children.contains(next),
+                None => children.is_empty(),
+            };
+            if !is_linear {
+                return Err(StError::NonLinearStack(branch_name.clone()));
+            }
+        }
+
+        if !ctx.repository.is_working_tree_clean()? {
+            return Err(StError::WorkingTreeDirty);
+        }
+
+        // Prompt the user for the new order of the stack.
+        let predefined = format!("{}\n{}", branches.join("\n"), REORDER_INSTRUCTIONS);
+        let edited = inquire::Editor::new("Reorder the branches of the stack")
+            .with_predefined_text(&predefined)
+            .with_file_extension(".txt")
+            .prompt()?;
+        let new_order = edited
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        // Validate that the new order is a permutation of the stack.
+        let unique = new_order.iter().collect::<HashSet<_>>();
+        if new_order.len() != branches.len()
+            || unique.len() != branches.len()
+            || !branches.iter().all(|b| unique.contains(b))
+        {
+            return Err(StError::InvalidReorder);
+        }
+        if new_order == branches {
+            println!("The order of the stack is unchanged.");
+            return Ok(());
+        }
+
+        Self::reorder(ctx, base, branches, &new_order)
+    }
+
+    /// Relinks the linear stack of `branches` on top of `base` in the new order, and replays each
+    /// branch's own commits onto its new parent.
+    fn reorder(
+        ctx: &mut StContext<'_>,
+        base: &str,
+        branches: &[String],
+        new_order: &[String],
+    ) -> StResult<()> {
+        // Snapshot the stack prior to relinking it, so that the reorder can be aborted.
+        let mut plan = RestackPlan::new(ctx, branches)?;
+
+        // Relink the stack in the new order, and replay each branch's own commits onto its new
+        // parent.
+        ctx.tree.reorder(base, new_order)?;
+        plan.steps
+            .make_contiguous()
+            .sort_by_key(|step| new_order.iter().position(|b| b == &step.branch));
+        let mut parent_name = base;
+        for branch_name in new_order.iter() {
+            plan.retarget(branch_name, parent_name);
+            parent_name = branch_name;
+        }
+        plan.use_cached_upstreams();
+
+        let mut summary = RestackSummary::default();
+        let result = ctx.run_restack_plan(plan, &mut summary);
+        println!("\n📚 Restack summary:\n{}", summary);
+        result?;
+
+        println!("Reordered the current stack.");
+
+        // Flag pull requests whose base branch changed.
+        let parents = |order: &[String]| {
+            std::iter::once(base)
+                .chain(order.iter().map(String::as_str))
+                .zip(order.iter())
+                .map(|(parent, branch)| (branch.clone(), parent.to_string()))
+                .collect::<Vec<_>>()
+        };
+        let old_parents = parents(branches);
+        for (branch_name, parent_name) in parents(new_order) {
+            let unchanged = old_parents.contains(&(branch_name.clone(), parent_name.clone()));
+            let remote = ctx.tree.get(&branch_name).and_then(|b| b.remote.as_ref());
+            if let (false, Some(remote)) = (unchanged, remote) {
+                println!(
+                    "The base of pull request #{} will be updated to `{}` on the next `{}`.",
+                    remote.pr_number,
+                    Color::Yellow.paint(&parent_name),
+                    Color::Blue.paint("st submit")
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ReorderCmd;
+    use crate::test_utils::TestRepo;
+
+    #[test]
+    fn reorder_swaps_commits() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        test_repo.create_branch(&mut ctx, "b", "b.txt", "b\n");
+
+        let branches = ["a".to_string(), "b".to_string()];
+        let new_order = ["b".to_string(), "a".to_string()];
+        ReorderCmd::reorder(&mut ctx, "main", &branches, &new_order).unwrap();
+
+        assert_eq!(test_repo.log("b"), ["b", "initial commit"]);
+        assert_eq!(test_repo.log("a"), ["a", "b", "initial commit"]);
+        assert_eq!(test_repo.read("b", "a.txt"), None);
+        assert_eq!(test_repo.read("a", "b.txt").as_deref(), Some("b\n"));
+
+        assert_eq!(ctx.tree.get("b").unwrap().parent.as_deref(), Some("main"));
+        assert_eq!(ctx.tree.get("a").unwrap().parent.as_deref(), Some("b"));
+        assert_eq!(
+            ctx.tree.get("a").unwrap().parent_oid_cache,
+            Some(test_repo.head("b").to_string())
+        );
+        assert!(!ctx.needs_restack("a").unwrap());
+
+        // The originally checked out branch is checked out again.
+        assert_eq!(test_repo.repo.head().unwrap().shorthand(), Some("b"));
+    }
+
+    #[test]
+    fn reorder_moves_tip_to_bottom() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        test_repo.create_branch(&mut ctx, "b", "b.txt", "b\n");
+        test_repo.create_branch(&mut ctx, "c", "c.txt", "c\n");
+
+        let branches = ["a", "b", "c"].map(String::from);
+        let new_order = ["c", "a", "b"].map(String::from);
+        ReorderCmd::reorder(&mut ctx, "main", &branches, &new_order).unwrap();
+
+        assert_eq!(test_repo.log("c"), ["c", "initial commit"]);
+        assert_eq!(test_repo.log("a"), ["a", "c", "initial commit"]);
+        assert_eq!(test_repo.log("b"), ["b", "a", "c", "initial commit"]);
+    }
+}
@@ -0,0 +1,108 @@
+//! `repair` subcommand.
+
+use crate::{
+    ctx::StContext,
+    errors::{StError, StResult},
+};
+use clap::Args;
+use git2::BranchType;
+use nu_ansi_term::Color;
+
+/// CLI arguments for the `repair` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct RepairCmd;
+
+impl RepairCmd {
+    /// Run the `repair` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        ctx.check_no_restack_in_progress()?;
+
+        let mut num_repaired = 0;
+        for branch_name in ctx.tree.branches()? {
+            let tracked_branch = ctx
+                .tree
+                .get(&branch_name)
+                .ok_or_else(|| StError::BranchNotTracked(branch_name.clone()))?;
+            let Some(parent_name) = tracked_branch.parent.clone() else {
+                continue;
+            };
+
+            // The fork point of a branch is the merge-base of its `HEAD` and its parent's `HEAD`.
+            let head = ctx
+                .repository
+                .find_branch(&branch_name, BranchType::Local)?
+                .get()
+                .target()
+                .ok_or(StError::BranchUnavailable)?;
+            let parent_head = ctx
+                .repository
+                .find_branch(&parent_name, BranchType::Local)?
+                .get()
+                .target()
+                .ok_or(StError::BranchUnavailable)?;
+            let Ok(merge_base) = ctx.repository.merge_base(head, parent_head) else {
+                println!(
+                    "⚠️ Branch `{}` shares no history with `{}`. Skipping.",
+                    Color::Green.paint(&branch_name),
+                    Color::Yellow.paint(&parent_name)
+                );
+                continue;
+            };
+
+            let merge_base = merge_base.to_string();
+            if tracked_branch.parent_oid_cache.as_ref() == Some(&merge_base) {
+                continue;
+            }
+
+            println!(
+                "Repaired the parent oid cache of branch `{}`: {} -> {}",
+                Color::Green.paint(&branch_name),
+                tracked_branch
+                    .parent_oid_cache
+                    .as_deref()
+                    .map_or("none", |oid| &oid[..7.min(oid.len())]),
+                &merge_base[..7]
+            );
+            ctx.tree
+                .get_mut(&branch_name)
+                .ok_or_else(|| StError::BranchNotTracked(branch_name.clone()))?
+                .parent_oid_cache = Some(merge_base);
+            num_repaired += 1;
+        }
+
+        if num_repaired == 0 {
+            println!("All parent oid caches are up to date.");
+        } else {
+            println!(
+                "\nRepaired {} branch{}. Run `{}` to restack any branches that are behind their parents.",
+                num_repaired,
+                if num_repaired != 1 { "es" } else { "" },
+                Color::Blue.paint("st restack --all")
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RepairCmd;
+    use crate::test_utils::TestRepo;
+
+    #[test]
+    fn repairs_dangling_parent_oid_cache() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        let a_head = test_repo.create_branch(&mut ctx, "a", "a.txt", "a\n");
+        test_repo.create_branch(&mut ctx, "b", "b.txt", "b\n");
+        ctx.tree.get_mut("b").unwrap().parent_oid_cache = Some("1".repeat(40));
+
+        RepairCmd.run(&mut ctx).unwrap();
+
+        assert_eq!(
+            ctx.tree.get("b").unwrap().parent_oid_cache,
+            Some(a_head.to_string())
+        );
+        assert!(!ctx.needs_restack("b").unwrap());
+    }
+}
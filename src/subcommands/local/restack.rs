@@ -1,15 +1,52 @@
 //! `restack` subcommand.
 
-use crate::{ctx::StContext, errors::StResult};
+use crate::{
+    ctx::{RestackSummary, StContext},
+    errors::StResult,
+    git::RepositoryExt,
+};
 use clap::Args;
 
 /// CLI arguments for the `restack` subcommand.
 #[derive(Debug, Clone, Eq, PartialEq, Args)]
-pub struct RestackCmd;
+pub struct RestackCmd {
+    /// Restack the current branch and all of its descendants, rather than the current stack.
+    #[clap(long, conflicts_with = "all")]
+    upstack: bool,
+    /// Restack every branch tracked with `st`.
+    #[clap(long)]
+    all: bool,
+    /// Report which branches would restack cleanly or conflict, without restacking them.
+    #[clap(long)]
+    check: bool,
+}
 
 impl RestackCmd {
     /// Run the `restack` subcommand.
-    pub fn run(self, mut ctx: StContext<'_>) -> StResult<()> {
-        ctx.restack()
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        ctx.check_no_restack_in_progress()?;
+
+        // Resolve the branches to restack, with parents listed before their children.
+        let branches = if self.all {
+            ctx.tree.branches()?
+        } else if self.upstack {
+            let current_branch = ctx.repository.current_branch_name()?;
+            ctx.tree.subtree(&current_branch)?
+        } else {
+            ctx.discover_stack()?
+        };
+
+        if self.check {
+            println!("🔮 Restack check:\n{}", ctx.check_restack(&branches)?);
+            return Ok(());
+        }
+
+        let mut summary = RestackSummary::default();
+        let result = ctx.restack_branches(&branches, &mut summary);
+
+        if !summary.is_empty() {
+            println!("\n📚 Restack summary:\n{}", summary);
+        }
+        result
     }
 }
@@ -0,0 +1,92 @@
+//! `snapshot` subcommand.
+
+use crate::{
+    ctx::{format_time_ago, StContext},
+    errors::StResult,
+};
+use clap::{Args, Subcommand};
+use nu_ansi_term::Color;
+
+/// CLI arguments for the `snapshot` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct SnapshotCmd {
+    /// The snapshot action to perform.
+    #[clap(subcommand)]
+    action: SnapshotAction,
+}
+
+/// Actions for the `snapshot` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Subcommand)]
+enum SnapshotAction {
+    /// Save a snapshot of all tracked stacks and their branches.
+    #[clap(visible_alias = "s")]
+    Save {
+        /// The name of the snapshot.
+        name: String,
+        /// Overwrite an existing snapshot with the same name.
+        #[clap(long, short)]
+        force: bool,
+    },
+    /// Restore all tracked stacks and their branches from a snapshot.
+    #[clap(visible_alias = "r")]
+    Restore {
+        /// The name of the snapshot.
+        name: String,
+        /// Also reset the trunk branch to its saved commit.
+        #[clap(long)]
+        trunk: bool,
+    },
+    /// List all saved snapshots.
+    #[clap(visible_alias = "ls")]
+    List,
+    /// Delete a snapshot.
+    #[clap(visible_alias = "d")]
+    Delete {
+        /// The name of the snapshot.
+        name: String,
+    },
+}
+
+impl SnapshotCmd {
+    /// Run the `snapshot` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        match self.action {
+            SnapshotAction::Save { name, force } => {
+                let snapshot = ctx.save_snapshot(&name, force)?;
+                println!(
+                    "Saved snapshot `{}` of {} branches.",
+                    Color::Blue.paint(&name),
+                    snapshot.heads.len()
+                );
+            }
+            SnapshotAction::Restore { name, trunk } => {
+                let snapshot = ctx.restore_snapshot(&name, trunk)?;
+                println!(
+                    "Restored snapshot `{}` of {} branches.",
+                    Color::Blue.paint(&name),
+                    snapshot.heads.len()
+                );
+                ctx.print_tree()?;
+            }
+            SnapshotAction::List => {
+                let snapshots = ctx.list_snapshots()?;
+                if snapshots.is_empty() {
+                    println!("No snapshots have been saved.");
+                }
+                for (name, timestamp, snapshot) in snapshots {
+                    println!(
+                        "📸 `{}` {} branches {}",
+                        Color::Blue.paint(name),
+                        snapshot.heads.len(),
+                        Color::DarkGray.paint(format!("({})", format_time_ago(timestamp)))
+                    );
+                }
+            }
+            SnapshotAction::Delete { name } => {
+                ctx.delete_snapshot(&name)?;
+                println!("Deleted snapshot `{}`.", Color::Blue.paint(&name));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+//! `split` subcommand.
+
+use crate::{
+    ctx::{RestackPlan, RestackSummary, StContext},
+    errors::{StError, StResult},
+    git::{diff_hunks, RepositoryExt},
+};
+use clap::Args;
+use git2::{BranchType, Commit, Oid};
+use nu_ansi_term::Color;
+use std::collections::HashSet;
+
+/// CLI arguments for the `split` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct SplitCmd {
+    /// Split the current branch into one stacked branch per commit.
+    #[clap(long, conflicts_with = "by_hunk", required_unless_present = "by_hunk")]
+    by_commit: bool,
+    /// Interactively split the current branch by selecting the hunks for each new branch.
+    #[clap(long)]
+    by_hunk: bool,
+}
+
+impl SplitCmd {
+    /// Run the `split` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        ctx.check_no_restack_in_progress()?;
+
+        // Gather metadata about the current branch.
+        let current_branch_name = ctx.repository.current_branch_name()?;
+        let tracked_branch = ctx
+            .tree
+            .get(&current_branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(current_branch_name.clone()))?
+            .clone();
+        let parent_name = tracked_branch
+            .parent
+            .ok_or(StError::CannotModifyTrunkBranch)?;
+        let parent_oid_cache = tracked_branch
+            .parent_oid_cache
+            .ok_or(StError::MissingParentOidCache)?;
+
+        if !ctx.repository.is_working_tree_clean()? {
+            return Err(StError::WorkingTreeDirty);
+        }
+
+        let commits = ctx.branch_commits(&current_branch_name)?;
+        let base = ctx
+            .repository
+            .find_commit(Oid::from_str(&parent_oid_cache)?)?;
+
+        // Snapshot the descendants of the branch, in case its `HEAD` commit is rewritten.
+        let descendants = ctx.tree.subtree(&current_branch_name)?[1..].to_vec();
+        let mut plan = RestackPlan::new(ctx, &descendants)?;
+        plan.use_cached_upstreams();
+
+        // Determine the new branches to insert below the current branch, as well as the new
+        // `HEAD` commit of the current branch, if it is rewritten.
+        let (parts, new_head) = if self.by_commit {
+            Self::split_by_commit(&current_branch_name, &commits)?
+        } else {
+            Self::split_by_hunk(ctx, &current_branch_name, &base, &commits)?
+        };
+
+        if parts.is_empty() {
+            println!(
+                "No new branches were split off of `{}`.",
+                Color::Green.paint(&current_branch_name)
+            );
+            return Ok(());
+        }
+
+        // Check every name before creating any branches, so that a taken name cannot leave the
+        // split half done.
+        for (i, (name, _)) in parts.iter().enumerate() {
+            Self::check_branch_name(ctx, name, &parts[..i])?;
+        }
+
+        // Create the new branches, and insert them into the stack tree in order.
+        let mut parent = (parent_name, base.id());
+        for (name, oid) in parts.iter() {
+            ctx.repository
+                .branch(name, &ctx.repository.find_commit(*oid)?, false)?;
+            ctx.tree.insert(&parent.0, &parent.1.to_string(), name)?;
+            println!(
+                "Created branch `{}` on top of `{}`.",
+                Color::Blue.paint(name),
+                Color::Blue.paint(&parent.0)
+            );
+            parent = (name.clone(), *oid);
+        }
+
+        // Move the current branch on top of the new branches.
+        ctx.tree.reparent(&current_branch_name, &parent.0)?;
+        ctx.tree
+            .get_mut(&current_branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(current_branch_name.clone()))?
+            .parent_oid_cache = Some(parent.1.to_string());
+
+        // If the current branch was rewritten, point it at its new `HEAD` commit and restack its
+        // descendants. The tree of the new `HEAD` commit is unchanged, so the working tree does
+        // not need to be updated.
+        if let Some(new_head) = new_head {
+            ctx.repository.reference(
+                format!("refs/heads/{}", current_branch_name).as_str(),
+                new_head,
+                true,
+                "st: split",
+            )?;
+
+            if !descendants.is_empty() {
+                let mut summary = RestackSummary::default();
+                let result = ctx.run_restack_plan(plan, &mut summary);
+                println!("\n📚 Restack summary:\n{}", summary);
+                result?;
+            }
+        }
+
+        println!(
+            "Split branch `{}` into {} branches.",
+            Color::Green.paint(&current_branch_name),
+            parts.len() + 1
+        );
+        Ok(())
+    }
+
+    /// Splits the branch into one branch per commit. The original branch keeps its final commit,
+    /// and is not rewritten.
+    fn split_by_commit(branch_name: &str, commits: &[Oid]) -> StResult<SplitParts> {
+        if commits.len() < 2 {
+            return Err(StError::NothingToSplit(branch_name.to_string(), "commits"));
+        }
+
+        let parts = commits[..commits.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(i, oid)| (format!("{}-{}", branch_name, i + 1), *oid))
+            .collect();
+        Ok((parts, None))
+    }
+
+    /// Interactively splits the branch by hunk. The user repeatedly selects hunks for new
+    /// branches, and the remaining hunks stay on the original branch, which is rewritten as a
+    /// single commit on top of the new branches.
+    fn split_by_hunk(
+        ctx: &StContext<'_>,
+        branch_name: &str,
+        base: &Commit<'_>,
+        commits: &[Oid],
+    ) -> StResult<SplitParts> {
+        let repo = ctx.repository;
+        let head = repo.find_commit(
+            *commits
+                .last()
+                .ok_or_else(|| StError::NothingToSplit(branch_name.to_string(), "hunks"))?,
+        )?;
+        let base_tree = base.tree()?;
+        let diff = repo.diff_tree_to_tree(Some(&base_tree), Some(&head.tree()?), None)?;
+
+        let mut remaining = diff_hunks(&diff)?;
+        if remaining.len() < 2 {
+            return Err(StError::NothingToSplit(branch_name.to_string(), "hunks"));
+        }
+
+        let sig = repo.signature()?;
+        let mut selected = HashSet::new();
+        let mut parent = base.clone();
+        let mut parts = Vec::new();
+        loop {
+            let prompt = format!(
+                "Select the hunks for new branch #{} (unselected hunks stay on `{}`)",
+                parts.len() + 1,
+                Color::Green.paint(branch_name)
+            );
+            let chosen = inquire::MultiSelect::new(prompt.as_str(), remaining.clone()).prompt()?;
+
+            // Stop splitting once no hunks, or all remaining hunks, are selected.
+            if chosen.is_empty() || chosen.len() == remaining.len() {
+                break;
+            }
+
+            let default_name = format!("{}-{}", branch_name, parts.len() + 1);
+            let name = loop {
+                let name = inquire::Text::new("Name of new branch:")
+                    .with_default(&default_name)
+                    .prompt()?;
+                match Self::check_branch_name(ctx, &name, &parts) {
+                    Ok(()) => break name,
+                    Err(e) => println!("{}", e),
+                }
+            };
+            let message = inquire::Text::new("Commit message:").prompt()?;
+
+            // Apply every hunk selected so far on top of the base tree.
+            selected.extend(chosen.iter().map(|h| h.index));
+            remaining.retain(|h| !selected.contains(&h.index));
+            let tree = repo.find_tree(repo.apply_hunks(&base_tree, &diff, &selected)?)?;
+            let oid = repo.commit(None, &sig, &sig, &message, &tree, &[&parent])?;
+
+            parent = repo.find_commit(oid)?;
+            parts.push((name, oid));
+        }
+
+        if parts.is_empty() {
+            return Ok((parts, None));
+        }
+
+        // Rewrite the original branch as a single commit containing the remaining hunks.
+        let message = inquire::Text::new(
+            format!("Commit message for `{}`:", Color::Green.paint(branch_name)).as_str(),
+        )
+        .with_default(head.summary().unwrap_or_default())
+        .prompt()?;
+        let new_head = repo.commit(None, &sig, &sig, &message, &head.tree()?, &[&parent])?;
+
+        Ok((parts, Some(new_head)))
+    }
+
+    /// Checks that a new branch can be created with the given name, which must not be taken by
+    /// a local branch, a tracked branch, or one of the other new branches.
+    fn check_branch_name(
+        ctx: &StContext<'_>,
+        name: &str,
+        others: &[(String, Oid)],
+    ) -> StResult<()> {
+        if ctx.tree.get(name).is_some() {
+            return Err(StError::BranchAlreadyTracked(name.to_string()));
+        }
+        if others.iter().any(|(other, _)| other == name)
+            || ctx.repository.find_branch(name, BranchType::Local).is_ok()
+        {
+            return Err(StError::BranchAlreadyExists(name.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// The new branches to insert below the split branch, ordered from the bottom of the stack
+/// upwards, along with the new `HEAD` commit of the split branch if it was rewritten.
+type SplitParts = (Vec<(String, Oid)>, Option<Oid>);
+
+#[cfg(test)]
+mod test {
+    use super::SplitCmd;
+    use crate::{errors::StError, test_utils::TestRepo};
+    use git2::BranchType;
+
+    const BY_COMMIT: SplitCmd = SplitCmd {
+        by_commit: true,
+        by_hunk: false,
+    };
+
+    #[test]
+    fn split_by_commit_stacks_branches() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "1\n");
+        test_repo.commit("a.txt", "2\n", "a 2");
+        test_repo.commit("a.txt", "3\n", "a 3");
+
+        BY_COMMIT.run(&mut ctx).unwrap();
+
+        assert_eq!(test_repo.log("a-1"), ["a", "initial commit"]);
+        assert_eq!(test_repo.log("a-2"), ["a 2", "a", "initial commit"]);
+        assert_eq!(test_repo.log("a"), ["a 3", "a 2", "a", "initial commit"]);
+        assert_eq!(ctx.tree.get("a-2").unwrap().parent.as_deref(), Some("a-1"));
+        assert_eq!(ctx.tree.get("a").unwrap().parent.as_deref(), Some("a-2"));
+        assert!(!ctx.needs_restack("a").unwrap());
+    }
+
+    #[test]
+    fn split_by_commit_checks_names_first() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "1\n");
+        test_repo.commit("a.txt", "2\n", "a 2");
+        test_repo.commit("a.txt", "3\n", "a 3");
+        test_repo.git(&["branch", "a-2", "main"]);
+
+        assert!(matches!(
+            BY_COMMIT.run(&mut ctx),
+            Err(StError::BranchAlreadyExists(name)) if name == "a-2"
+        ));
+        assert!(test_repo
+            .repo
+            .find_branch("a-1", BranchType::Local)
+            .is_err());
+        assert!(ctx.tree.get("a-1").is_none());
+    }
+}
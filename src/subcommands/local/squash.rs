@@ -0,0 +1,140 @@
+//! `squash` subcommand.
+
+use crate::{
+    ctx::{RestackPlan, RestackSummary, StContext},
+    errors::{StError, StResult},
+    git::RepositoryExt,
+};
+use clap::Args;
+use git2::Oid;
+use nu_ansi_term::Color;
+
+/// CLI arguments for the `squash` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct SquashCmd {
+    /// Specify a commit message for the squashed commit, rather than editing it.
+    #[clap(short, long)]
+    message: Option<String>,
+}
+
+impl SquashCmd {
+    /// Run the `squash` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        ctx.check_no_restack_in_progress()?;
+
+        // Gather metadata about the current branch.
+        let current_branch_name = ctx.repository.current_branch_name()?;
+        let tracked_branch = ctx
+            .tree
+            .get(&current_branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(current_branch_name.clone()))?;
+        if tracked_branch.parent.is_none() {
+            return Err(StError::CannotModifyTrunkBranch);
+        }
+        let parent_oid_cache = tracked_branch
+            .parent_oid_cache
+            .clone()
+            .ok_or(StError::MissingParentOidCache)?;
+
+        if !ctx.repository.is_working_tree_clean()? {
+            return Err(StError::WorkingTreeDirty);
+        }
+
+        let commits = ctx
+            .branch_commits(&current_branch_name)?
+            .into_iter()
+            .map(|oid| ctx.repository.find_commit(oid))
+            .collect::<Result<Vec<_>, _>>()?;
+        if commits.len() < 2 {
+            println!(
+                "Branch `{}` has fewer than two commits. Nothing to squash.",
+                Color::Green.paint(&current_branch_name)
+            );
+            return Ok(());
+        }
+        let (first, head) = (&commits[0], &commits[commits.len() - 1]);
+
+        // Prompt the user for the message of the squashed commit, or use the provided message.
+        let message = match self.message {
+            Some(message) => message,
+            None => {
+                let predefined = commits
+                    .iter()
+                    .map(|c| c.message().unwrap_or_default().trim().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+                inquire::Editor::new("Message for the squashed commit")
+                    .with_predefined_text(&predefined)
+                    .prompt()?
+            }
+        };
+
+        // Snapshot the descendants of the branch before its `HEAD` commit is rewritten.
+        let descendants = ctx.tree.subtree(&current_branch_name)?[1..].to_vec();
+        let mut plan = RestackPlan::new(ctx, &descendants)?;
+        plan.use_cached_upstreams();
+
+        // Create the squashed commit on top of the parent. Its tree is identical to the branch's
+        // current `HEAD` commit, so the working tree does not need to be updated.
+        let parent = ctx
+            .repository
+            .find_commit(Oid::from_str(&parent_oid_cache)?)?;
+        let committer = ctx.repository.signature()?;
+        let squashed = ctx.repository.commit(
+            None,
+            &first.author(),
+            &committer,
+            &message,
+            &head.tree()?,
+            &[&parent],
+        )?;
+        ctx.repository.reference(
+            format!("refs/heads/{}", current_branch_name).as_str(),
+            squashed,
+            true,
+            "st: squash",
+        )?;
+
+        println!(
+            "Squashed {} commits on branch `{}`.",
+            commits.len(),
+            Color::Green.paint(&current_branch_name)
+        );
+
+        // Restack the descendants onto the squashed commit.
+        if !descendants.is_empty() {
+            let mut summary = RestackSummary::default();
+            let result = ctx.run_restack_plan(plan, &mut summary);
+            println!("\n📚 Restack summary:\n{}", summary);
+            result?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SquashCmd;
+    use crate::test_utils::TestRepo;
+
+    #[test]
+    fn squash_restacks_descendants() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        test_repo.create_branch(&mut ctx, "a", "a.txt", "1\n");
+        test_repo.commit("a.txt", "2\n", "a 2");
+        test_repo.create_branch(&mut ctx, "b", "b.txt", "b\n");
+        test_repo.checkout("a");
+
+        SquashCmd {
+            message: Some("squashed".to_string()),
+        }
+        .run(&mut ctx)
+        .unwrap();
+
+        assert_eq!(test_repo.log("a"), ["squashed", "initial commit"]);
+        assert_eq!(test_repo.read("a", "a.txt").unwrap(), "2\n");
+        assert_eq!(test_repo.log("b"), ["b", "squashed", "initial commit"]);
+        assert!(!ctx.needs_restack("b").unwrap());
+    }
+}
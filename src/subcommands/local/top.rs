@@ -0,0 +1,17 @@
+//! `top` subcommand.
+
+use crate::{ctx::StContext, errors::StResult, git::RepositoryExt};
+use clap::Args;
+
+/// CLI arguments for the `top` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct TopCmd;
+
+impl TopCmd {
+    /// Run the `top` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        let current_branch = ctx.repository.current_branch_name()?;
+        let target = ctx.resolve_upstack(&current_branch, usize::MAX)?;
+        ctx.checkout_tracked_branch(&target)
+    }
+}
@@ -14,7 +14,7 @@ pub struct TrackCmd;
 
 impl TrackCmd {
     /// Run the `track` subcommand.
-    pub fn run(self, mut ctx: StContext<'_>) -> StResult<()> {
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
         // Gather metadata about the current branch.
         let current_branch = ctx.repository.current_branch()?;
         let current_branch_name = ctx.repository.current_branch_name()?;
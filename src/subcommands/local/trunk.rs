@@ -0,0 +1,15 @@
+//! `trunk` subcommand.
+
+use crate::{ctx::StContext, errors::StResult};
+use clap::Args;
+
+/// CLI arguments for the `trunk` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct TrunkCmd;
+
+impl TrunkCmd {
+    /// Run the `trunk` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        ctx.checkout_tracked_branch(&ctx.tree.trunk_name)
+    }
+}
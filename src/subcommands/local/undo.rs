@@ -0,0 +1,52 @@
+//! `undo` subcommand.
+
+use crate::{
+    ctx::{Journal, JournalOutcome, StContext},
+    errors::StResult,
+};
+use clap::Args;
+use nu_ansi_term::Color;
+
+/// CLI arguments for the `undo` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct UndoCmd {
+    /// List the operations that can be undone, rather than undoing the most recent one.
+    #[clap(long, short)]
+    list: bool,
+}
+
+impl UndoCmd {
+    /// Run the `undo` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        ctx.disable_journal();
+
+        if self.list {
+            let journal = Journal::load(ctx.repository)?;
+            if journal.entries.is_empty() {
+                println!("No operations have been recorded.");
+            } else {
+                println!("📜 Operation history (most recent first):");
+                for (i, entry) in journal.entries.iter().rev().enumerate() {
+                    println!("{:>3}. {}", i + 1, entry);
+                }
+            }
+            if !journal.redo.is_empty() {
+                println!(
+                    "\n{} undone operation{} can be re-applied with `{}`.",
+                    journal.redo.len(),
+                    if journal.redo.len() != 1 { "s" } else { "" },
+                    Color::Blue.paint("st redo")
+                );
+            }
+            return Ok(());
+        }
+
+        ctx.check_no_restack_in_progress()?;
+        match ctx.undo_operation()? {
+            JournalOutcome::Applied(entry) => println!("Undid {}", entry),
+            JournalOutcome::Empty => println!("Nothing to undo."),
+            JournalOutcome::Aborted => println!("Undo aborted. No branches were changed."),
+        }
+        Ok(())
+    }
+}
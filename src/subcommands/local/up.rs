@@ -0,0 +1,21 @@
+//! `up` subcommand.
+
+use crate::{ctx::StContext, errors::StResult, git::RepositoryExt};
+use clap::Args;
+
+/// CLI arguments for the `up` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct UpCmd {
+    /// Number of branches to move upstack.
+    #[clap(index = 1, default_value_t = 1)]
+    steps: usize,
+}
+
+impl UpCmd {
+    /// Run the `up` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        let current_branch = ctx.repository.current_branch_name()?;
+        let target = ctx.resolve_upstack(&current_branch, self.steps)?;
+        ctx.checkout_tracked_branch(&target)
+    }
+}
@@ -5,11 +5,14 @@ use clap::Subcommand;
 
 mod local;
 use local::{
-    CheckoutCmd, ConfigCmd, CreateCmd, DeleteCmd, LogCmd, RestackCmd, TrackCmd, UntrackCmd,
+    AbortCmd, AbsorbCmd, BottomCmd, CheckoutCmd, ConfigCmd, ContinueCmd, CreateCmd, DeleteCmd,
+    DoctorCmd, DownCmd, FoldCmd, LogCmd, ModifyCmd, MoveCmd, RedoCmd, RenameCmd, ReorderCmd,
+    RepairCmd, RestackCmd, SnapshotCmd, SplitCmd, SquashCmd, TopCmd, TrackCmd, TrunkCmd, UndoCmd,
+    UntrackCmd, UpCmd,
 };
 
 mod remote;
-use remote::{StatusCmd, SubmitCmd, SyncCmd};
+use remote::{AdoptCmd, GetCmd, MetadataCmd, StatusCmd, SubmitCmd, SyncCmd};
 
 #[derive(Debug, Clone, Eq, PartialEq, Subcommand)]
 pub enum Subcommands {
@@ -22,21 +25,85 @@ pub enum Subcommands {
     /// Checkout a branch that is tracked with `st`.
     #[clap(visible_alias = "co")]
     Checkout(CheckoutCmd),
+    /// Move upstack by one or more branches.
+    #[clap(visible_alias = "u")]
+    Up(UpCmd),
+    /// Move downstack by one or more branches.
+    #[clap(visible_alias = "dn")]
+    Down(DownCmd),
+    /// Move to the tip of the current stack.
+    #[clap(visible_alias = "t")]
+    Top(TopCmd),
+    /// Move to the first branch above trunk in the current stack.
+    #[clap(visible_alias = "b")]
+    Bottom(BottomCmd),
+    /// Move to the trunk branch.
+    #[clap(visible_alias = "tk")]
+    Trunk(TrunkCmd),
     /// Create and track a new branch within the current stack.
     #[clap(visible_alias = "c")]
     Create(CreateCmd),
+    /// Amend or add a commit to the current branch, and restack all branches above it.
+    #[clap(visible_aliases = ["m", "amend"])]
+    Modify(ModifyCmd),
     /// Delete a branch that is tracked with `st`.
     #[clap(visible_aliases = ["d", "del"])]
     Delete(DeleteCmd),
-    /// Restack the the current stack.
+    /// Restack the current stack, the current branch's upstack, or all stacks.
     #[clap(visible_aliases = ["r", "sr"])]
     Restack(RestackCmd),
+    /// Move the current branch and its descendants on top of another branch.
+    #[clap(visible_alias = "mv")]
+    Move(MoveCmd),
+    /// Rename the current branch, keeping its place in the stack.
+    #[clap(visible_alias = "rn")]
+    Rename(RenameCmd),
+    /// Reorder the branches of the current stack in an editor.
+    #[clap(visible_alias = "ro")]
+    Reorder(ReorderCmd),
+    /// Split the current branch into several stacked branches.
+    #[clap(visible_alias = "sp")]
+    Split(SplitCmd),
+    /// Squash all commits on the current branch into a single commit.
+    #[clap(visible_alias = "sq")]
+    Squash(SquashCmd),
+    /// Fold the current branch into its parent, and delete it.
+    #[clap(visible_alias = "f")]
+    Fold(FoldCmd),
+    /// Absorb staged changes into the branches in the current stack that introduced the lines.
+    #[clap(visible_alias = "ab")]
+    Absorb(AbsorbCmd),
+    /// Continue a restack that stopped due to conflicts.
+    #[clap(visible_alias = "cont")]
+    Continue(ContinueCmd),
+    /// Abort a restack that stopped due to conflicts, restoring all branches.
+    Abort(AbortCmd),
+    /// Undo the most recent `st` operation, restoring the stack and the branches it moved.
+    #[clap(visible_alias = "un")]
+    Undo(UndoCmd),
+    /// Re-apply the most recently undone `st` operation.
+    Redo(RedoCmd),
+    /// Save, restore, or list named snapshots of all tracked stacks.
+    #[clap(visible_alias = "snap")]
+    Snapshot(SnapshotCmd),
+    /// Recompute the parent oid cache of every tracked branch from its merge-base with its parent.
+    Repair(RepairCmd),
+    /// Check the tracked stacks for inconsistencies, and offer to fix them.
+    #[clap(visible_alias = "dr")]
+    Doctor(DoctorCmd),
     /// Print a tree of all tracked stacks.
     #[clap(visible_aliases = ["l", "ls"])]
     Log(LogCmd),
     /// Show the status of the current stack on GitHub.
     #[clap(visible_aliases = ["st", "stat"])]
     Status(StatusCmd),
+    /// Exchange the tracked stacks with a remote.
+    #[clap(visible_alias = "meta")]
+    Metadata(MetadataCmd),
+    /// Rebuild the tracked stacks from open pull requests on GitHub.
+    Adopt(AdoptCmd),
+    /// Check out the stack of a pull request on GitHub, and track its branches.
+    Get(GetCmd),
     /// Track the current branch on top of a tracked stack node.
     #[clap(visible_alias = "tr")]
     Track(TrackCmd),
@@ -50,17 +117,40 @@ pub enum Subcommands {
 
 impl Subcommands {
     /// Run the subcommand with the given store.
-    pub async fn run(self, ctx: StContext<'_>) -> StResult<()> {
+    pub async fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
         match self {
             // Remote
             Self::Sync(args) => args.run(ctx).await,
             Self::Submit(args) => args.run(ctx).await,
             Self::Status(args) => args.run(ctx).await,
+            Self::Metadata(args) => args.run(ctx),
+            Self::Adopt(args) => args.run(ctx).await,
+            Self::Get(args) => args.run(ctx).await,
             // Local
             Self::Checkout(args) => args.run(ctx),
+            Self::Up(args) => args.run(ctx),
+            Self::Down(args) => args.run(ctx),
+            Self::Top(args) => args.run(ctx),
+            Self::Bottom(args) => args.run(ctx),
+            Self::Trunk(args) => args.run(ctx),
             Self::Create(args) => args.run(ctx),
+            Self::Modify(args) => args.run(ctx),
             Self::Delete(args) => args.run(ctx),
             Self::Restack(args) => args.run(ctx),
+            Self::Move(args) => args.run(ctx),
+            Self::Rename(args) => args.run(ctx).await,
+            Self::Reorder(args) => args.run(ctx),
+            Self::Split(args) => args.run(ctx),
+            Self::Squash(args) => args.run(ctx),
+            Self::Fold(args) => args.run(ctx),
+            Self::Absorb(args) => args.run(ctx),
+            Self::Continue(args) => args.run(ctx),
+            Self::Abort(args) => args.run(ctx),
+            Self::Undo(args) => args.run(ctx),
+            Self::Redo(args) => args.run(ctx),
+            Self::Snapshot(args) => args.run(ctx),
+            Self::Repair(args) => args.run(ctx),
+            Self::Doctor(args) => args.run(ctx).await,
             Self::Log(args) => args.run(ctx),
             Self::Track(args) => args.run(ctx),
             Self::Untrack(args) => args.run(ctx),
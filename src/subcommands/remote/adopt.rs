@@ -0,0 +1,92 @@
+//! `adopt` subcommand.
+
+use crate::{ctx::StContext, errors::StResult};
+use clap::Args;
+use nu_ansi_term::Color;
+use octocrab::{params::State, Octocrab};
+
+/// CLI arguments for the `adopt` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct AdoptCmd {
+    /// Adopt the pull requests opened by this GitHub user, rather than by the authenticated user.
+    #[clap(short, long)]
+    author: Option<String>,
+    /// The remote to fetch branches from (defaults to "origin").
+    #[clap(short, long = "remote")]
+    remote: Option<String>,
+}
+
+impl AdoptCmd {
+    /// Run the `adopt` subcommand.
+    pub async fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        ctx.check_no_restack_in_progress()?;
+
+        // Override the remote name if provided.
+        ctx.set_remote_name(self.remote.clone());
+        let remote_name = ctx.remote_name.clone().unwrap_or("origin".to_string());
+
+        // Establish the GitHub API client.
+        let gh_client = Octocrab::builder()
+            .personal_token(ctx.cfg.github_token.clone())
+            .build()?;
+        let (owner, repo) = ctx.owner_and_repository()?;
+        let author = match self.author.clone() {
+            Some(author) => author,
+            None => gh_client.current().user().await?.login,
+        };
+
+        // Gather the open pull requests of the author whose branches live in this repository.
+        println!(
+            "🐙 Fetching open pull requests by `{}`...",
+            Color::Blue.paint(&author)
+        );
+        let page = gh_client
+            .pulls(&owner, &repo)
+            .list()
+            .state(State::Open)
+            .per_page(100)
+            .send()
+            .await?;
+        let open = gh_client
+            .all_pages(page)
+            .await?
+            .into_iter()
+            .filter(|pr| pr.user.as_ref().is_some_and(|u| u.login == author))
+            .filter(|pr| {
+                let head_repo = pr.head.repo.as_ref().map(|r| r.id);
+                let base_repo = pr.base.repo.as_ref().map(|r| r.id);
+                if head_repo != base_repo {
+                    println!(
+                        "Skipping pull request #{}, whose branch lives in a fork.",
+                        pr.number
+                    );
+                }
+                head_repo == base_repo
+            })
+            .collect::<Vec<_>>();
+
+        // Adopt the pull requests, rebuilding each stack from the bottom up.
+        let num_open = open.len();
+        let remaining = ctx
+            .adopt_pull_requests(&gh_client, &remote_name, open)
+            .await?;
+        let num_adopted = num_open - remaining.len();
+
+        for pr in remaining.iter() {
+            println!(
+                "⚠️ Skipped pull request #{}, as its base branch `{}` is not tracked, and is not the head of another pull request by `{}`.",
+                pr.number,
+                Color::Yellow.paint(&pr.base.ref_field),
+                Color::Blue.paint(&author)
+            );
+        }
+
+        println!(
+            "\n🧸 Adopted {} pull request{}. Run `{}` to see the rebuilt stacks.",
+            num_adopted,
+            if num_adopted != 1 { "s" } else { "" },
+            Color::Blue.paint("st log")
+        );
+        Ok(())
+    }
+}
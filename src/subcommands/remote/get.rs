@@ -0,0 +1,169 @@
+//! `get` subcommand.
+
+use crate::{
+    constants::ST_COMMENT_FOOTER,
+    ctx::StContext,
+    errors::{StError, StResult},
+    git::RepositoryExt,
+};
+use clap::Args;
+use nu_ansi_term::Color;
+use octocrab::{
+    models::{pulls::PullRequest, IssueState},
+    params::State,
+    Octocrab,
+};
+
+/// CLI arguments for the `get` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct GetCmd {
+    /// The number of the pull request to check out.
+    number: u64,
+    /// The remote to fetch branches from (defaults to "origin").
+    #[clap(short, long = "remote")]
+    remote: Option<String>,
+}
+
+impl GetCmd {
+    /// Run the `get` subcommand.
+    pub async fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        ctx.check_no_restack_in_progress()?;
+
+        // Override the remote name if provided.
+        ctx.set_remote_name(self.remote.clone());
+        let remote_name = ctx.remote_name.clone().unwrap_or("origin".to_string());
+
+        // Establish the GitHub API client.
+        let gh_client = Octocrab::builder()
+            .personal_token(ctx.cfg.github_token.clone())
+            .build()?;
+        let (owner, repo) = ctx.owner_and_repository()?;
+        let pulls = gh_client.pulls(&owner, &repo);
+
+        println!("🐙 Fetching pull request #{}...", self.number);
+        let pr = pulls.get(self.number).await?;
+        if !Self::is_open(&pr) {
+            return Err(StError::PullRequestClosed(pr.number));
+        }
+        if Self::is_from_fork(&pr) {
+            return Err(StError::PullRequestFromFork(pr.number));
+        }
+        let branch_name = pr.head.ref_field.clone();
+
+        // Walk the base branches down until a tracked branch is reached, which is at the latest
+        // the trunk branch.
+        let mut base_name = pr.base.ref_field.clone();
+        let mut stack = vec![pr];
+        while ctx.tree.get(&base_name).is_none()
+            && !stack.iter().any(|p| p.head.ref_field == base_name)
+        {
+            let Some(base_pr) = pulls
+                .list()
+                .state(State::Open)
+                .head(format!("{}:{}", owner, base_name))
+                .send()
+                .await?
+                .items
+                .into_iter()
+                .next()
+            else {
+                break;
+            };
+            base_name = base_pr.base.ref_field.clone();
+            stack.push(base_pr);
+        }
+
+        // Gather the rest of the stack, including the dependents of the pull request, from the
+        // stack overview comment left by `st submit`.
+        let page = gh_client
+            .issues(&owner, &repo)
+            .list_comments(self.number)
+            .per_page(100)
+            .send()
+            .await?;
+        let listed = gh_client
+            .all_pages(page)
+            .await?
+            .into_iter()
+            .filter_map(|c| c.body)
+            .find(|body| body.contains(ST_COMMENT_FOOTER))
+            .map(|body| Self::parse_stack_comment(&body))
+            .unwrap_or_default();
+        for number in listed {
+            if stack.iter().any(|p| p.number == number) {
+                continue;
+            }
+            let listed_pr = pulls.get(number).await?;
+            if Self::is_open(&listed_pr) && !Self::is_from_fork(&listed_pr) {
+                stack.push(listed_pr);
+            }
+        }
+
+        // Fetch and track the branches of the stack, from the bottom up.
+        let num_found = stack.len();
+        let remaining = ctx
+            .adopt_pull_requests(&gh_client, &remote_name, stack)
+            .await?;
+        for pr in remaining.iter() {
+            println!(
+                "⚠️ Skipped pull request #{}, as its base branch `{}` is not tracked, and is not the head of another open pull request.",
+                pr.number,
+                Color::Yellow.paint(&pr.base.ref_field)
+            );
+        }
+
+        if ctx.tree.get(&branch_name).is_none() {
+            return Ok(());
+        }
+        ctx.repository.checkout_branch(&branch_name)?;
+        println!(
+            "\n🧸 Tracked {} of {} pull request{} in the stack, and checked out `{}`.",
+            num_found - remaining.len(),
+            num_found,
+            if num_found != 1 { "s" } else { "" },
+            Color::Green.paint(&branch_name)
+        );
+        Ok(())
+    }
+
+    /// Returns whether the pull request is still open.
+    fn is_open(pr: &PullRequest) -> bool {
+        matches!(pr.state, Some(IssueState::Open)) && pr.merged_at.is_none()
+    }
+
+    /// Returns whether the head branch of the pull request lives in a different repository than
+    /// its base branch.
+    fn is_from_fork(pr: &PullRequest) -> bool {
+        pr.head.repo.as_ref().map(|r| r.id) != pr.base.repo.as_ref().map(|r| r.id)
+    }
+
+    /// Parses the pull request numbers listed in a stack overview comment, as rendered by
+    /// `st submit`.
+    fn parse_stack_comment(body: &str) -> Vec<u64> {
+        body.lines()
+            .filter_map(|line| line.strip_prefix("* #"))
+            .filter_map(|rest| rest.split_whitespace().next()?.parse().ok())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::GetCmd;
+    use crate::{subcommands::remote::SubmitCmd, test_utils::TestRepo, tree::RemoteMetadata};
+
+    #[test]
+    fn parses_rendered_stack_comment() {
+        let test_repo = TestRepo::new();
+        let mut ctx = test_repo.ctx();
+        ctx.tree.insert("main", "0", "a").unwrap();
+        ctx.tree.insert("a", "1", "b").unwrap();
+        ctx.tree.get_mut("a").unwrap().remote = Some(RemoteMetadata::new(None, 10));
+        ctx.tree.get_mut("b").unwrap().remote = Some(RemoteMetadata::new(None, 11));
+
+        let stack = ["main".to_string(), "a".to_string(), "b".to_string()];
+        let comment = SubmitCmd::render_pr_comment(&ctx, "a", &stack).unwrap();
+        assert!(comment.contains("* #10 👈\n"));
+        assert_eq!(GetCmd::parse_stack_comment(&comment), [11, 10]);
+    }
+}
@@ -0,0 +1,107 @@
+//! `metadata` subcommand.
+
+use crate::{
+    ctx::{MetadataSync, StContext},
+    errors::StResult,
+};
+use clap::{Args, Subcommand};
+use nu_ansi_term::Color;
+
+/// CLI arguments for the `metadata` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct MetadataCmd {
+    /// The metadata action to perform.
+    #[clap(subcommand)]
+    action: MetadataAction,
+}
+
+/// Actions for the `metadata` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Subcommand)]
+enum MetadataAction {
+    /// Merge the stacks on the remote into the local stacks, and push the result.
+    Push {
+        /// The remote to push to (defaults to "origin").
+        #[clap(short, long = "remote")]
+        remote: Option<String>,
+    },
+    /// Merge the stacks on the remote into the local stacks.
+    Pull {
+        /// The remote to pull from (defaults to "origin").
+        #[clap(short, long = "remote")]
+        remote: Option<String>,
+    },
+}
+
+impl MetadataCmd {
+    /// Run the `metadata` subcommand.
+    pub fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
+        ctx.check_no_restack_in_progress()?;
+
+        match self.action {
+            MetadataAction::Push { remote } => {
+                ctx.set_remote_name(remote);
+                let remote_name = ctx.remote_name.clone().unwrap_or("origin".to_string());
+                let sync = ctx.push_metadata(&remote_name)?;
+                print_sync(&sync, &remote_name);
+                println!(
+                    "🐙 Pushed stack metadata to remote `{}`.",
+                    Color::Blue.paint(&remote_name)
+                );
+            }
+            MetadataAction::Pull { remote } => {
+                ctx.set_remote_name(remote);
+                let remote_name = ctx.remote_name.clone().unwrap_or("origin".to_string());
+                let sync = ctx.pull_metadata(&remote_name)?;
+                print_sync(&sync, &remote_name);
+                match sync {
+                    MetadataSync::Missing => println!(
+                        "Remote `{}` has no stack metadata. Push it with `{}`.",
+                        Color::Blue.paint(&remote_name),
+                        Color::Blue.paint("st metadata push")
+                    ),
+                    MetadataSync::UpToDate => println!("Stack metadata is up to date."),
+                    MetadataSync::Updated { .. } => println!(
+                        "🐙 Pulled stack metadata from remote `{}`.",
+                        Color::Blue.paint(&remote_name)
+                    ),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Prints the branches that were created, conflicted, or could not be fetched while merging
+/// remote metadata.
+fn print_sync(sync: &MetadataSync, remote_name: &str) {
+    let MetadataSync::Updated {
+        conflicts,
+        created,
+        remote_only,
+    } = sync
+    else {
+        return;
+    };
+
+    for branch_name in created {
+        println!(
+            "Created branch `{}` from `{}`.",
+            Color::Green.paint(branch_name),
+            Color::Yellow.paint(format!("{}/{}", remote_name, branch_name))
+        );
+    }
+    for branch_name in remote_only {
+        println!(
+            "Branch `{}` is not available on `{}`. It is kept in the metadata, but not tracked locally.",
+            Color::Green.paint(branch_name),
+            Color::Blue.paint(remote_name)
+        );
+    }
+    for branch_name in conflicts {
+        println!(
+            "⚠️ Branch `{}` was changed both locally and on `{}`. Kept the local version.",
+            Color::Green.paint(branch_name),
+            Color::Blue.paint(remote_name)
+        );
+    }
+}
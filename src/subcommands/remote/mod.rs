@@ -8,3 +8,12 @@ pub use sync::SyncCmd;
 
 mod status;
 pub use status::StatusCmd;
+
+mod metadata;
+pub use metadata::MetadataCmd;
+
+mod adopt;
+pub use adopt::AdoptCmd;
+
+mod get;
+pub use get::GetCmd;
@@ -18,7 +18,7 @@ pub struct StatusCmd {
 
 impl StatusCmd {
     /// Run the `status` subcommand.
-    pub async fn run(self, mut ctx: StContext<'_>) -> StResult<()> {
+    pub async fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
         // Override the remote name if provided.
         ctx.set_remote_name(self.remote.clone());
 
@@ -58,7 +58,7 @@ impl StatusCmd {
                 let is_merged = pr_info.merged_at.is_some();
                 let is_closed = pr_info
                     .state
-                    .map_or(true, |s| matches!(s, IssueState::Closed));
+                    .is_none_or(|s| matches!(s, IssueState::Closed));
 
                 if is_draft {
                     row.push("📝 Draft".to_string());
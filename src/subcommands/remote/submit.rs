@@ -1,6 +1,7 @@
 //! `submit` subcommand.
 
 use crate::{
+    constants::ST_COMMENT_FOOTER,
     ctx::StContext,
     errors::{StError, StResult},
     git::RepositoryExt,
@@ -24,7 +25,7 @@ pub struct SubmitCmd {
 
 impl SubmitCmd {
     /// Run the `submit` subcommand.
-    pub async fn run(self, mut ctx: StContext<'_>) -> StResult<()> {
+    pub async fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
         // Override the remote name if provided.
         ctx.set_remote_name(self.remote.clone());
 
@@ -40,19 +41,18 @@ impl SubmitCmd {
 
         // Perform pre-flight checks.
         println!("🔍 Checking for closed pull requests...");
-        self.pre_flight(&mut ctx, &stack, &mut pulls).await?;
+        self.pre_flight(ctx, &stack, &mut pulls).await?;
 
         // Submit the stack.
         println!(
             "\n🐙 Submitting changes to remote `{}`...",
             Color::Blue.paint(ctx.remote_name.as_deref().unwrap_or("origin"))
         );
-        self.submit_stack(&mut ctx, &mut pulls, &owner, &repo)
-            .await?;
+        self.submit_stack(ctx, &mut pulls, &owner, &repo).await?;
 
         // Update the stack navigation comments on the PRs.
         println!("\n📝 Updating stack navigation comments...");
-        self.update_pr_comments(&mut ctx, gh_client.issues(owner, repo), &stack)
+        self.update_pr_comments(ctx, gh_client.issues(owner, repo), &stack)
             .await?;
 
         println!("\n🧙💫 All pull requests up to date.");
@@ -81,7 +81,7 @@ impl SubmitCmd {
             println!(
                 "Deleted {} closed pull request{}. Run `{}` to re-stack the branches.",
                 Color::Red.paint(num_closed.to_string()),
-                (num_closed != 1).then_some("s").unwrap_or_default(),
+                if num_closed != 1 { "s" } else { "" },
                 Color::Blue.paint("st restack")
             );
         }
@@ -176,6 +176,10 @@ impl SubmitCmd {
                 tracked_branch.remote =
                     Some(RemoteMetadata::new(ctx.remote_name.clone(), pr_info.number));
 
+                // Save the link right away, so that the pull request is not opened again if a
+                // later submission fails.
+                ctx.save()?;
+
                 // Print success message.
                 let pr_link = format!(
                     "https://github.com/{}/{}/pull/{}",
@@ -266,7 +270,7 @@ impl SubmitCmd {
     }
 
     /// Renders the PR comment body for the current stack.
-    fn render_pr_comment(
+    pub(crate) fn render_pr_comment(
         ctx: &StContext<'_>,
         current_branch: &str,
         stack: &[String],
@@ -285,17 +289,18 @@ impl SubmitCmd {
                 comment.push_str(&format!(
                     "* #{}{}\n",
                     remote.pr_number,
-                    (branch == current_branch)
-                        .then_some(" 👈")
-                        .unwrap_or_default()
+                    if branch == current_branch {
+                        " 👈"
+                    } else {
+                        ""
+                    }
                 ));
             }
         }
         comment.push_str(format!("* `{}`\n", ctx.tree.trunk_name).as_str());
 
-        comment.push_str(
-            "\n_This comment was automatically generated by [`st`](https://github.com/clabby/st)._",
-        );
+        comment.push('\n');
+        comment.push_str(ST_COMMENT_FOOTER);
         Ok(comment)
     }
 }
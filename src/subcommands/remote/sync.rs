@@ -8,6 +8,7 @@ use crate::{
 use clap::Args;
 use nu_ansi_term::Color;
 use octocrab::{pulls::PullRequestHandler, Octocrab};
+use std::collections::HashSet;
 
 /// CLI arguments for the `sync` subcommand.
 #[derive(Debug, Clone, Eq, PartialEq, Args)]
@@ -19,7 +20,7 @@ pub struct SyncCmd {
 
 impl SyncCmd {
     /// Run the `sync` subcommand.
-    pub async fn run(self, mut ctx: StContext<'_>) -> StResult<()> {
+    pub async fn run(self, ctx: &mut StContext<'_>) -> StResult<()> {
         // Override the remote name if provided.
         ctx.set_remote_name(self.remote.clone());
 
@@ -31,7 +32,7 @@ impl SyncCmd {
         let mut pulls = gh_client.pulls(&owner, &repo);
 
         // Perform pre-flight checks.
-        self.pre_flight(&mut ctx, &mut pulls).await?;
+        self.pre_flight(ctx, &mut pulls).await?;
 
         // Resolve all branches in the stack tree after the deletions have been applied.
         let branches = ctx.tree.branches()?;
@@ -42,7 +43,7 @@ impl SyncCmd {
             "\n🐙 Pulling latest changes from remote `{}`...",
             Color::Blue.paint(ctx.remote_name.as_deref().unwrap_or("origin"))
         );
-        self.pull_changes(&mut ctx, branches.as_slice()).await?;
+        self.pull_changes(ctx, branches.as_slice()).await?;
 
         // Attempt to restack the current stack with the new changes.
         println!("\n🧙 Restacking branches...");
@@ -121,12 +122,20 @@ impl SyncCmd {
         Ok(())
     }
 
-    /// Restacks the provided branches.
+    /// Restacks the provided branches, skipping those that are predicted to conflict.
     async fn try_restack_branches(
         &self,
-        mut ctx: StContext<'_>,
+        ctx: &mut StContext<'_>,
         branches: &[String],
     ) -> StResult<()> {
+        // Check which branches would conflict up front, rather than aborting rebases halfway.
+        let conflicting = ctx
+            .check_restack(branches)?
+            .conflicting()
+            .map(String::from)
+            .collect::<HashSet<_>>();
+
+        let mut num_conflicts = 0;
         for branch in branches {
             if ctx.needs_restack(branch)? {
                 let tracked_branch = ctx
@@ -139,26 +148,35 @@ impl SyncCmd {
                     .expect("Parent must exist")
                     .clone();
 
-                let mut num_conflicts = 0;
-                if ctx.restack_branch(branch, &parent_name).is_err() {
-                    ctx.repository.abort_rebase()?;
+                if conflicting.contains(branch) {
                     println!(
-                        "Failed to restack branch `{}` onto `{}`.",
+                        "Skipping branch `{}`, which would conflict with `{}`.",
                         Color::Green.paint(branch),
                         Color::Yellow.paint(parent_name)
                     );
                     num_conflicts += 1;
+                    continue;
                 }
 
-                if num_conflicts > 0 {
+                if ctx.restack_branch(branch, &parent_name).is_err() {
+                    ctx.abort_interrupted_restack()?;
                     println!(
-                        "Failed to restack {} branches. You can resolve conflicts by checking out the stack and running `{}`.",
-                        Color::Red.paint(num_conflicts.to_string()),
-                        Color::Blue.paint("st restack")
+                        "Failed to restack branch `{}` onto `{}`.",
+                        Color::Green.paint(branch),
+                        Color::Yellow.paint(parent_name)
                     );
+                    num_conflicts += 1;
                 }
             }
         }
+
+        if num_conflicts > 0 {
+            println!(
+                "Failed to restack {} branches. You can resolve conflicts by checking out the stack and running `{}`.",
+                Color::Red.paint(num_conflicts.to_string()),
+                Color::Blue.paint("st restack")
+            );
+        }
         Ok(())
     }
 }
@@ -0,0 +1,178 @@
+//! Utilities for testing `st` against temporary git repositories.
+
+use crate::{
+    config::{RepoConfig, StConfig},
+    ctx::StContext,
+    git::RepositoryExt,
+};
+use git2::{BranchType, Oid, Repository, RepositoryInitOptions};
+use std::{path::Path, process::Command};
+use tempfile::TempDir;
+
+/// A git repository in a temporary directory, with a single commit on its `main` branch.
+pub(crate) struct TestRepo {
+    /// The repository.
+    pub repo: Repository,
+    /// The directory holding the repository, which is removed when dropped.
+    pub dir: TempDir,
+}
+
+impl TestRepo {
+    /// Creates a new [TestRepo], with `main` checked out.
+    pub(crate) fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let mut opts = RepositoryInitOptions::new();
+        opts.initial_head("main");
+        let repo = Repository::init_opts(dir.path(), &opts).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "st").unwrap();
+        config.set_str("user.email", "st@localhost").unwrap();
+
+        let test_repo = Self { repo, dir };
+        test_repo.commit("README.md", "st\n", "initial commit");
+        test_repo
+    }
+
+    /// Creates a new [TestRepo] by cloning the repository at `url`, with `main` checked out.
+    pub(crate) fn cloned(url: &Path) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::clone(url.to_str().unwrap(), dir.path()).unwrap();
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "st").unwrap();
+        config.set_str("user.email", "st@localhost").unwrap();
+        Self { repo, dir }
+    }
+
+    /// Creates a bare repository in a temporary directory, registers it as the `origin` remote,
+    /// and pushes `main` to it.
+    ///
+    /// ## Returns
+    /// - `TempDir` - The directory holding the remote, which is removed when dropped.
+    pub(crate) fn add_remote(&self) -> TempDir {
+        let remote_dir = tempfile::tempdir().unwrap();
+        Repository::init_bare(remote_dir.path())
+            .unwrap()
+            .set_head("refs/heads/main")
+            .unwrap();
+        self.repo
+            .remote("origin", remote_dir.path().to_str().unwrap())
+            .unwrap();
+        self.git(&["push", "-q", "origin", "main"]);
+        remote_dir
+    }
+
+    /// Runs a `git` command in the repository, panicking if it fails.
+    pub(crate) fn git(&self, args: &[&str]) {
+        let status = Command::new("git")
+            .current_dir(self.dir.path())
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "`git {}` failed", args.join(" "));
+    }
+
+    /// Loads the [StContext] for the repository, setting it up with `main` as the trunk branch
+    /// if needed.
+    pub(crate) fn ctx(&self) -> StContext<'_> {
+        self.ctx_with(RepoConfig::default())
+    }
+
+    /// Loads the [StContext] for the repository with the passed [RepoConfig], setting it up
+    /// with `main` as the trunk branch if needed.
+    pub(crate) fn ctx_with(&self, repo_cfg: RepoConfig) -> StContext<'_> {
+        StContext::load_or_initialize(StConfig::default(), repo_cfg, &self.repo, || {
+            Ok("main".to_string())
+        })
+        .unwrap()
+    }
+
+    /// Writes `contents` to `path` in the working tree, and commits it onto the checked out
+    /// branch.
+    pub(crate) fn commit(&self, path: &str, contents: &str, message: &str) -> Oid {
+        self.write(path, contents);
+        let mut index = self.repo.index().unwrap();
+        index.add_path(Path::new(path)).unwrap();
+        index.write().unwrap();
+
+        let tree = self.repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = self.repo.signature().unwrap();
+        let parent = self.repo.head().ok().map(|h| h.peel_to_commit().unwrap());
+        self.repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parent.iter().collect::<Vec<_>>(),
+            )
+            .unwrap()
+    }
+
+    /// Writes `contents` to `path` in the working tree, without staging it.
+    pub(crate) fn write(&self, path: &str, contents: &str) {
+        std::fs::write(self.dir.path().join(path), contents).unwrap();
+    }
+
+    /// Creates a branch on top of the checked out branch, tracks it in the [StContext], checks
+    /// it out, and commits `contents` to `path` onto it.
+    pub(crate) fn create_branch(
+        &self,
+        ctx: &mut StContext<'_>,
+        name: &str,
+        path: &str,
+        contents: &str,
+    ) -> Oid {
+        let parent = self.repo.current_branch_name().unwrap();
+        let parent_oid = self.head(&parent);
+        self.repo
+            .branch(name, &self.repo.find_commit(parent_oid).unwrap(), false)
+            .unwrap();
+        ctx.tree
+            .insert(&parent, &parent_oid.to_string(), name)
+            .unwrap();
+        self.checkout(name);
+        self.commit(path, contents, name)
+    }
+
+    /// Checks out the passed branch.
+    pub(crate) fn checkout(&self, name: &str) {
+        self.repo.force_checkout_branch(name).unwrap();
+    }
+
+    /// Returns the `HEAD` commit of the passed branch.
+    pub(crate) fn head(&self, name: &str) -> Oid {
+        self.repo
+            .find_branch(name, BranchType::Local)
+            .unwrap()
+            .get()
+            .target()
+            .unwrap()
+    }
+
+    /// Returns the summaries of the first-parent history of the passed branch, newest first.
+    pub(crate) fn log(&self, name: &str) -> Vec<String> {
+        let mut commit = self.repo.find_commit(self.head(name)).unwrap();
+        let mut log = vec![commit.summary().unwrap().to_string()];
+        while let Ok(parent) = commit.parent(0) {
+            log.push(parent.summary().unwrap().to_string());
+            commit = parent;
+        }
+        log
+    }
+
+    /// Returns the contents of `path` in the tree of the passed branch, if it exists.
+    pub(crate) fn read(&self, name: &str, path: &str) -> Option<String> {
+        let tree = self
+            .repo
+            .find_commit(self.head(name))
+            .unwrap()
+            .tree()
+            .unwrap();
+        let entry = tree.get_path(Path::new(path)).ok()?;
+        let blob = self.repo.find_blob(entry.id()).unwrap();
+        Some(String::from_utf8(blob.content().to_vec()).unwrap())
+    }
+}
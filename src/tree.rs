@@ -139,16 +139,282 @@ impl StackTree {
         Ok(branch)
     }
 
+    /// Renames a branch, updating its parent's list of children and each of its children's parent.
+    ///
+    /// ## Takes
+    /// - `branch_name` - The current name of the branch.
+    /// - `new_name` - The new name of the branch.
+    ///
+    /// ## Returns
+    /// - `Ok(())` if the branch was successfully renamed.
+    /// - `Err(_)` if the branch does not exist, or a branch named `new_name` is already tracked.
+    pub fn rename(&mut self, branch_name: &str, new_name: &str) -> StResult<()> {
+        if self.branches.contains_key(new_name) {
+            return Err(StError::BranchAlreadyTracked(new_name.to_string()));
+        }
+
+        // Re-key the branch.
+        let mut branch = self
+            .branches
+            .remove(branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(branch_name.to_string()))?;
+        branch.name = new_name.to_string();
+
+        // Update the parent's list of children.
+        if let Some(parent) = branch
+            .parent
+            .as_ref()
+            .and_then(|p| self.branches.get_mut(p))
+        {
+            parent.children.remove(branch_name);
+            parent.children.insert(new_name.to_string());
+        }
+
+        // Point each of the children at the new name.
+        branch.children.iter().try_for_each(|child_name| {
+            self.branches
+                .get_mut(child_name)
+                .ok_or_else(|| StError::BranchNotTracked(child_name.to_string()))?
+                .parent = Some(new_name.to_string());
+            Ok::<_, StError>(())
+        })?;
+
+        if self.trunk_name == branch_name {
+            self.trunk_name = new_name.to_string();
+        }
+        self.branches.insert(new_name.to_string(), branch);
+
+        Ok(())
+    }
+
+    /// Moves a branch, along with all of its descendants, on top of a new parent branch.
+    ///
+    /// The branch's [RemoteMetadata] and parent [git2::Oid] cache are left untouched.
+    ///
+    /// ## Takes
+    /// - `branch_name` - The name of the branch to move.
+    /// - `parent_name` - The name of the new parent branch.
+    ///
+    /// ## Returns
+    /// - `Ok(())` if the branch was successfully moved.
+    /// - `Err(_)` if either branch does not exist, or the new parent is the branch itself or one of
+    ///   its descendants.
+    pub fn reparent(&mut self, branch_name: &str, parent_name: &str) -> StResult<()> {
+        if branch_name == self.trunk_name {
+            return Err(StError::CannotMoveTrunkBranch);
+        } else if !self.branches.contains_key(parent_name) {
+            return Err(StError::BranchNotTracked(parent_name.to_string()));
+        } else if self.subtree(branch_name)?.iter().any(|b| b == parent_name) {
+            return Err(StError::InvalidMoveTarget(
+                branch_name.to_string(),
+                parent_name.to_string(),
+            ));
+        }
+
+        // Point the branch at its new parent.
+        let branch = self
+            .branches
+            .get_mut(branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(branch_name.to_string()))?;
+        let old_parent_name = branch.parent.replace(parent_name.to_string());
+
+        // Remove the branch from the old parent's children list.
+        if let Some(old_parent) = old_parent_name.and_then(|p| self.branches.get_mut(&p)) {
+            old_parent.children.remove(branch_name);
+        }
+
+        // Add the branch to the new parent's children list.
+        self.branches
+            .get_mut(parent_name)
+            .ok_or_else(|| StError::BranchNotTracked(parent_name.to_string()))?
+            .children
+            .insert(branch_name.to_string());
+
+        Ok(())
+    }
+
+    /// Relinks a linear chain of branches in the passed order. The first branch is placed on top of
+    /// the parent of the chain, and each following branch is placed on top of the one before it.
+    ///
+    /// The branches' [RemoteMetadata] and parent [git2::Oid] caches are left untouched.
+    ///
+    /// ## Takes
+    /// - `parent_name` - The name of the branch that the chain sits on top of.
+    /// - `branch_names` - The branches of the chain, in their new order.
+    ///
+    /// ## Returns
+    /// - `Ok(())` if the chain was successfully relinked.
+    /// - `Err(_)` if any of the branches do not exist.
+    pub fn reorder(&mut self, parent_name: &str, branch_names: &[String]) -> StResult<()> {
+        // Detach every branch in the chain onto the parent first, so that no intermediate link
+        // can create a cycle.
+        branch_names
+            .iter()
+            .try_for_each(|branch_name| self.reparent(branch_name, parent_name))?;
+
+        branch_names
+            .windows(2)
+            .try_for_each(|pair| self.reparent(&pair[1], &pair[0]))
+    }
+
+    /// Performs a three-way merge of two [StackTree]s that diverged from a common base, branch by
+    /// branch.
+    ///
+    /// A branch changed on only one side takes that side's version, including deletions. If a
+    /// branch was changed differently on both sides, `ours` wins and the branch is reported as a
+    /// conflict. Branches whose merged parent links form a cycle take `ours`' parent, and are
+    /// reported as conflicts as well. The children of each branch are rebuilt from the merged
+    /// parent links, and branches whose parent no longer exists are moved on top of trunk.
+    ///
+    /// ## Returns
+    /// - `Ok((tree, conflicts))` - The merged tree, and the names of the branches that conflicted.
+    /// - `Err(_)` - If the trees do not share the same trunk branch.
+    pub fn merge(base: &Self, ours: &Self, theirs: &Self) -> StResult<(Self, Vec<String>)> {
+        if ours.trunk_name != theirs.trunk_name {
+            return Err(StError::TrunkMismatch(
+                ours.trunk_name.clone(),
+                theirs.trunk_name.clone(),
+            ));
+        }
+
+        let mut names = ours
+            .branches
+            .keys()
+            .chain(theirs.branches.keys())
+            .chain(base.branches.keys())
+            .cloned()
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+
+        // Compare branches without their children, which are derived from the parent links.
+        let local = |tree: &Self, name: &str| {
+            tree.branches.get(name).map(|b| TrackedBranch {
+                children: HashSet::new(),
+                ..b.clone()
+            })
+        };
+
+        let mut merged = Self {
+            trunk_name: ours.trunk_name.clone(),
+            branches: HashMap::new(),
+        };
+        let mut conflicts = Vec::new();
+        for name in names {
+            let (b, o, t) = (local(base, &name), local(ours, &name), local(theirs, &name));
+            let resolved = if o == t || t == b {
+                o
+            } else if o == b {
+                t
+            } else {
+                conflicts.push(name.clone());
+                o.or(t)
+            };
+            if let Some(branch) = resolved {
+                merged.branches.insert(name, branch);
+            }
+        }
+
+        // Links taken from both sides can form a cycle, e.g. if we moved `a` on top of `b` while
+        // they moved `b` on top of `a`. Break them by falling back to our parents.
+        while let Some(cycle) = merged.find_cycle() {
+            let trunk_name = merged.trunk_name.clone();
+            let mut changed = false;
+            for name in cycle.iter() {
+                let our_parent = match ours.branches.get(name) {
+                    Some(branch) => branch.parent.clone(),
+                    None => Some(trunk_name.clone()),
+                };
+                let branch = merged.branches.get_mut(name).expect("Cycle members exist");
+                if branch.parent != our_parent {
+                    branch.parent = our_parent;
+                    changed = true;
+                }
+                if !conflicts.contains(name) {
+                    conflicts.push(name.clone());
+                }
+            }
+
+            // Our own tree never holds a cycle, but fall back to trunk in case it does.
+            if !changed {
+                if let Some(branch) = merged.branches.get_mut(&cycle[0]) {
+                    branch.parent = Some(trunk_name);
+                }
+            }
+        }
+        conflicts.sort();
+
+        merged.relink();
+        Ok((merged, conflicts))
+    }
+
+    /// Rebuilds the children of each branch from the parent links. Branches whose parent is not
+    /// tracked are moved on top of trunk.
+    pub fn relink(&mut self) {
+        for branch in self.branches.values_mut() {
+            branch.children.clear();
+        }
+
+        let links = self
+            .branches
+            .values()
+            .filter_map(|b| b.parent.clone().map(|p| (b.name.clone(), p)))
+            .collect::<Vec<_>>();
+        for (name, parent) in links {
+            let parent = if self.branches.contains_key(&parent) {
+                parent
+            } else {
+                let trunk_name = self.trunk_name.clone();
+                if let Some(branch) = self.branches.get_mut(&name) {
+                    branch.parent = Some(trunk_name.clone());
+                }
+                trunk_name
+            };
+            if let Some(parent) = self.branches.get_mut(&parent) {
+                parent.children.insert(name);
+            }
+        }
+    }
+
+    /// Returns the branches of a cycle in the parent links, if there is one.
+    fn find_cycle(&self) -> Option<Vec<String>> {
+        let mut names = self.branches.keys().collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            let mut path: Vec<String> = Vec::new();
+            let mut current = Some(name.clone());
+            while let Some(branch_name) = current {
+                if let Some(start) = path.iter().position(|b| b == &branch_name) {
+                    return Some(path.split_off(start));
+                }
+                current = self
+                    .branches
+                    .get(&branch_name)
+                    .and_then(|b| b.parent.clone());
+                path.push(branch_name);
+            }
+        }
+        None
+    }
+
     /// Returns a vector of branch names in the stack graph. The vector is filled recursively, meaning that children are
     /// guaranteed to be listed after their parents.
     pub fn branches(&self) -> StResult<Vec<String>> {
+        self.subtree(&self.trunk_name)
+    }
+
+    /// Returns a vector containing the passed branch and all of its descendants. The vector is filled recursively,
+    /// meaning that children are guaranteed to be listed after their parents.
+    pub fn subtree(&self, branch_name: &str) -> StResult<Vec<String>> {
         let mut branch_names = Vec::new();
-        self.fill_branches(&self.trunk_name, &mut branch_names)?;
+        self.fill_branches(branch_name, &mut branch_names)?;
         Ok(branch_names)
     }
 
-    /// Fills a vector with the trunk branch and its children. The resulting vector is filled recursively, meaning that
+    /// Fills a vector with the passed branch and its children. The resulting vector is filled recursively, meaning that
     /// children are guaranteed to be listed after their parents.
+    ///
+    /// Children that are not tracked, or that were already visited through a cycle, are skipped. See `st doctor`.
     fn fill_branches(&self, name: &str, branch_names: &mut Vec<String>) -> StResult<()> {
         let current = self
             .branches
@@ -156,10 +422,12 @@ impl StackTree {
             .ok_or_else(|| StError::BranchNotTracked(name.to_string()))?;
 
         branch_names.push(current.name.clone());
-        current
-            .children
-            .iter()
-            .try_for_each(|child| self.fill_branches(child, branch_names))
+        for child in current.children.iter() {
+            if self.branches.contains_key(child) && !branch_names.contains(child) {
+                self.fill_branches(child, branch_names)?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -229,3 +497,128 @@ impl RemoteMetadata {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::StackTree;
+
+    /// Builds a tree of `main -> a -> b`, with `c` on top of `main`.
+    fn sample_tree() -> StackTree {
+        let mut tree = StackTree::new("main".to_string());
+        tree.insert("main", "0", "a").unwrap();
+        tree.insert("a", "1", "b").unwrap();
+        tree.insert("main", "0", "c").unwrap();
+        tree
+    }
+
+    #[test]
+    fn reparent_moves_subtree() {
+        let mut tree = sample_tree();
+        tree.reparent("a", "c").unwrap();
+
+        assert_eq!(tree.get("a").unwrap().parent.as_deref(), Some("c"));
+        assert!(tree.get("c").unwrap().children.contains("a"));
+        assert!(!tree.get("main").unwrap().children.contains("a"));
+        assert_eq!(tree.get("b").unwrap().parent.as_deref(), Some("a"));
+        assert_eq!(
+            tree.get("a").unwrap().parent_oid_cache.as_deref(),
+            Some("0")
+        );
+    }
+
+    #[test]
+    fn reparent_rejects_descendants() {
+        let mut tree = sample_tree();
+        assert!(tree.reparent("a", "b").is_err());
+        assert!(tree.reparent("a", "a").is_err());
+        assert!(tree.reparent("main", "c").is_err());
+        assert_eq!(tree, sample_tree());
+    }
+
+    #[test]
+    fn rename_relinks_parent_and_children() {
+        let mut tree = sample_tree();
+        tree.rename("a", "x").unwrap();
+
+        assert!(tree.get("a").is_none());
+        assert_eq!(tree.get("x").unwrap().name, "x");
+        assert!(tree.get("main").unwrap().children.contains("x"));
+        assert!(!tree.get("main").unwrap().children.contains("a"));
+        assert_eq!(tree.get("b").unwrap().parent.as_deref(), Some("x"));
+        assert!(tree.rename("x", "c").is_err());
+    }
+
+    #[test]
+    fn merge_combines_changes_per_branch() {
+        let base = sample_tree();
+
+        // We add `d` on top of `c`, and they move `b` on top of `c` and delete `a`.
+        let mut ours = base.clone();
+        ours.insert("c", "2", "d").unwrap();
+        let mut theirs = base.clone();
+        theirs.reparent("b", "c").unwrap();
+        theirs.delete("a").unwrap();
+
+        let (merged, conflicts) = StackTree::merge(&base, &ours, &theirs).unwrap();
+        assert!(conflicts.is_empty());
+        assert!(merged.get("a").is_none());
+        assert_eq!(merged.get("b").unwrap().parent.as_deref(), Some("c"));
+        assert_eq!(merged.get("d").unwrap().parent.as_deref(), Some("c"));
+        assert_eq!(
+            merged.get("c").unwrap().children,
+            ["b", "d"].into_iter().map(String::from).collect()
+        );
+    }
+
+    #[test]
+    fn merge_prefers_ours_on_conflict() {
+        let base = sample_tree();
+        let mut ours = base.clone();
+        ours.reparent("b", "c").unwrap();
+        let mut theirs = base.clone();
+        theirs.reparent("b", "main").unwrap();
+
+        let (merged, conflicts) = StackTree::merge(&base, &ours, &theirs).unwrap();
+        assert_eq!(conflicts, vec!["b".to_string()]);
+        assert_eq!(merged.get("b").unwrap().parent.as_deref(), Some("c"));
+    }
+
+    #[test]
+    fn merge_breaks_cycles_with_ours() {
+        let base = sample_tree();
+
+        // We move `a` on top of `c`, and they move `c` on top of `a`.
+        let mut ours = base.clone();
+        ours.reparent("a", "c").unwrap();
+        let mut theirs = base.clone();
+        theirs.reparent("c", "a").unwrap();
+
+        let (merged, conflicts) = StackTree::merge(&base, &ours, &theirs).unwrap();
+        assert_eq!(conflicts, vec!["a".to_string(), "c".to_string()]);
+        assert_eq!(merged.get("a").unwrap().parent.as_deref(), Some("c"));
+        assert_eq!(merged.get("c").unwrap().parent.as_deref(), Some("main"));
+        assert_eq!(merged.branches().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn merge_rejects_trunk_mismatch() {
+        let base = sample_tree();
+        let theirs = StackTree::new("master".to_string());
+        assert!(StackTree::merge(&base, &base, &theirs).is_err());
+    }
+
+    #[test]
+    fn reorder_relinks_chain() {
+        let mut tree = sample_tree();
+        tree.reorder("main", &["b".to_string(), "a".to_string()])
+            .unwrap();
+
+        assert_eq!(tree.get("b").unwrap().parent.as_deref(), Some("main"));
+        assert_eq!(tree.get("a").unwrap().parent.as_deref(), Some("b"));
+        assert!(tree.get("a").unwrap().children.is_empty());
+        assert_eq!(
+            tree.get("main").unwrap().children,
+            ["b", "c"].into_iter().map(String::from).collect()
+        );
+    }
+}